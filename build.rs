@@ -0,0 +1,18 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use std::env;
+use std::process::Command;
+
+// the tests against the cdylib are ignored without the tools they run.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=CC");
+    println!("cargo:rustc-check-cfg=cfg(has_cc)");
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    if Command::new(cc).arg("--version").output().is_ok() {
+        println!("cargo:rustc-cfg=has_cc");
+    }
+    println!("cargo:rustc-check-cfg=cfg(has_python)");
+    if env::var_os("CARGO_FEATURE_PYTHON").is_some() && Command::new("python3").arg("--version").output().is_ok() {
+        println!("cargo:rustc-cfg=has_python");
+    }
+}
@@ -0,0 +1,87 @@
+/* (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License. */
+
+#ifndef TEFF_H
+#define TEFF_H
+
+/* generated by cbindgen from src/capi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define TEFF_N_KINDS 34
+
+#define TEFF_OK 0
+
+#define TEFF_ERROR_NULL -1
+
+#define TEFF_ERROR_TILE -2
+
+#define TEFF_ERROR_HAND -3
+
+#define TEFF_ERROR_ENGINE -4
+
+#define TEFF_ENGINE_SEARCH 0
+
+#define TEFF_ENGINE_PLAYOUT 1
+
+#define TEFF_ENGINE_UCT 2
+
+typedef struct TeffTileSet TeffTileSet;
+
+typedef struct TeffConfig {
+  int32_t engine;
+  size_t depth;
+  size_t n_samples;
+  uint64_t seed;
+} TeffConfig;
+
+typedef struct TeffDiscard {
+  int32_t tile;
+  bool riichi;
+  double score;
+  double points;
+  size_t count;
+} TeffDiscard;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+struct TeffTileSet *teff_tile_set_new(void);
+
+void teff_tile_set_free(struct TeffTileSet *set);
+
+struct TeffTileSet *teff_tile_set_clone(const struct TeffTileSet *set);
+
+int32_t teff_tile_set_get(const struct TeffTileSet *set, int32_t tile);
+
+int32_t teff_tile_set_set(struct TeffTileSet *set, int32_t tile, int32_t n);
+
+size_t teff_tile_set_count(const struct TeffTileSet *set);
+
+struct TeffTileSet *teff_tile_set_parse(const char *text, size_t *error_pos);
+
+size_t teff_tile_set_format(const struct TeffTileSet *set, char *buf, size_t size);
+
+int32_t teff_shanten(const struct TeffTileSet *hand);
+
+int32_t teff_ukeire(const struct TeffTileSet *hand,
+                    const struct TeffTileSet *visible,
+                    size_t *counts);
+
+void teff_config_default(struct TeffConfig *config);
+
+int32_t teff_analyze_discards(const struct TeffTileSet *hand,
+                              const struct TeffTileSet *visible,
+                              const struct TeffConfig *config,
+                              struct TeffDiscard *results,
+                              size_t capacity);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* TEFF_H */
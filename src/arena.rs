@@ -0,0 +1,66 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::game::*;
+use rand::SeedableRng;
+use std::*;
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub n_games: usize,
+    pub n_rounds: usize,
+    pub n_wins: usize,
+    pub n_deal_ins: usize,
+    // 0 for the top.
+    pub rank_sum: usize,
+    pub score_sum: isize,
+}
+
+impl Stats {
+    pub fn mean_rank(&self) -> f64 {
+        1.0 + self.rank_sum as f64 / self.n_games as f64
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        self.n_wins as f64 / self.n_rounds as f64
+    }
+
+    pub fn deal_in_rate(&self) -> f64 {
+        self.n_deal_ins as f64 / self.n_rounds as f64
+    }
+
+    pub fn mean_score(&self) -> f64 {
+        self.score_sum as f64 / self.n_games as f64
+    }
+}
+
+// plays `n_games` games, the `i`-th dealt from the seed `seed + i`, and returns the stats of each strategy.  the
+// strategies move to the next seat every game.
+pub fn run(config: &GameConfig, strategies: &mut [&mut dyn Strategy], n_games: usize, seed: u64) -> Vec<Stats> {
+    let n = strategies.len();
+    let mut stats = vec![Stats::default(); n];
+    for i in 0..n_games {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+        let result = play_game(config, strategies, &mut rng);
+        // the strategy at `seat` is the `(seat + i) % n`-th one.
+        let index = |seat: usize| (seat + i) % n;
+        for seat in 0..N_PLAYERS {
+            let s = &mut stats[index(seat)];
+            s.n_games += 1;
+            s.n_rounds += result.rounds.len();
+            s.rank_sum += result.ranks[seat];
+            s.score_sum += result.scores[seat];
+        }
+        for r in result.rounds.iter() {
+            match r.outcome {
+                Outcome::Tsumo { winner, .. } => stats[index(winner)].n_wins += 1,
+                Outcome::Ron { winner, from, .. } => {
+                    stats[index(winner)].n_wins += 1;
+                    stats[index(from)].n_deal_ins += 1;
+                }
+                Outcome::Ryuukyoku { .. } => (),
+            }
+        }
+        strategies.rotate_left(1);
+    }
+    strategies.rotate_left(n - n_games % n);
+    stats
+}
@@ -1,83 +1,821 @@
 // (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::arena;
+use crate::danger;
+use crate::game::*;
 use crate::playout;
+use crate::score::*;
 use crate::search;
+use crate::shanten;
+use crate::strategy;
+use crate::table::*;
 use crate::tile::*;
 use crate::uct;
+use crate::wait;
+use rand::SeedableRng;
+use serde_json::{json, Value};
 use std::*;
 
-pub fn analyze_hand(hand: &mut TileSet) {
-    println!("Hand: {}", format_tile_set(hand));
+// the engine settings of `analyze_hand()` and `benchmark()`.
+#[derive(Clone, Debug)]
+pub struct Params {
+    // the max depth of the exhaustive search, and the depth of the playouts.
+    pub depth: usize,
+    pub playout_depth: usize,
+    pub n_samples: usize,
+    // "search", "playout" or "uct", or `None` for all of them.
+    pub engine: Option<String>,
+}
 
-    let n_tiles = hand.count();
-    if n_tiles % 3 != 2 {
-        println!("  # of tiles must be 3n + 2.");
-        return;
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            depth: 3,
+            playout_depth: 1,
+            n_samples: 1 << 21,
+            engine: None,
+        }
     }
+}
 
-    let mut wall = TileSet::new();
-    for i in 0..wall.len() {
-        *wall.tile_mut(i) = 4 - hand.tile(i);
+impl Params {
+    pub(crate) fn uses(&self, engine: &str) -> bool {
+        self.engine.as_deref().is_none_or(|e| e == engine)
     }
+}
 
-    let (score, _) = search::discard_tile(hand, &mut wall, 0);
-    println!("  min-mean # of non-meld tiles, depth = 0:");
-    println!("       {:>11.8}", score);
-    for i in 1..4 {
-        let (_, mut discards) = search::discard_tile_parallel(hand, &mut wall, i);
-        discards.sort_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap());
-        println!("  min-mean # of non-meld tiles, depth = {}:", i);
-        for (tile, score) in discards {
-            println!("    {} {:>11.8}", format_tile(tile), score);
+// the output of `analyze_hand()`.  only the text is printed while analyzing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+// the playouts whose estimates are plain means of the samples are run in batches, to estimate their errors.
+const N_BATCHES: usize = 4;
+
+// an estimate by an engine, with its standard error if it is sampled.
+#[derive(Clone, Debug)]
+pub struct Estimate {
+    pub name: &'static str,
+    pub value: f64,
+    pub std_err: Option<f64>,
+}
+
+// the estimates of a discard, of a winning tile for the waits, or of the hand itself if `tile` is `None`.
+#[derive(Clone, Debug)]
+pub struct Choice {
+    pub tile: Option<usize>,
+    pub riichi: bool,
+    pub estimates: Vec<Estimate>,
+}
+
+// the results of an engine with a setting, e.g. the search of a depth.
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub engine: &'static str,
+    pub depth: Option<usize>,
+    pub n_samples: Option<usize>,
+    pub elapsed: time::Duration,
+    pub choices: Vec<Choice>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    pub hand: TileSet,
+    // the unseen tiles the engines draw from.
+    pub wall: TileSet,
+    pub n_live: usize,
+    pub params: Params,
+    pub sections: Vec<Section>,
+    pub elapsed: time::Duration,
+}
+
+fn estimate(name: &'static str, value: f64) -> Estimate {
+    Estimate {
+        name,
+        value,
+        std_err: None,
+    }
+}
+
+fn sampled(name: &'static str, (value, std_err): (f64, f64)) -> Estimate {
+    Estimate {
+        name,
+        value,
+        std_err: Some(std_err),
+    }
+}
+
+fn choice(tile: Option<usize>, estimates: Vec<Estimate>) -> Choice {
+    Choice {
+        tile,
+        riichi: false,
+        estimates,
+    }
+}
+
+fn section(
+    engine: &'static str,
+    depth: Option<usize>,
+    n_samples: Option<usize>,
+    start: time::Instant,
+    choices: Vec<Choice>,
+) -> Section {
+    Section {
+        engine,
+        depth,
+        n_samples,
+        elapsed: start.elapsed(),
+        choices,
+    }
+}
+
+// runs `f` on `N_BATCHES` batches of the samples, returning the mean of each estimate by the key with its standard
+// error.  only for the estimates which are plain means of the samples, where the batches change nothing else.
+fn batch_means<K: Ord>(n_samples: usize, mut f: impl FnMut(usize) -> Vec<(K, Vec<f64>)>) -> Vec<(K, Vec<(f64, f64)>)> {
+    let mut acc = collections::BTreeMap::new();
+    for _ in 0..N_BATCHES {
+        for (key, values) in f(cmp::max(n_samples / N_BATCHES, 1)) {
+            acc.entry(key).or_insert_with(Vec::new).push(values);
         }
     }
+    acc.into_iter()
+        .map(|(key, batches)| {
+            let n = batches.len() as f64;
+            let stats = (0..batches[0].len())
+                .map(|i| {
+                    let mean = batches.iter().map(|b| b[i]).sum::<f64>() / n;
+                    let var = batches.iter().map(|b| (b[i] - mean).powi(2)).sum::<f64>() / (n * (n - 1.0));
+                    (mean, var.sqrt())
+                })
+                .collect();
+            (key, stats)
+        })
+        .collect()
+}
+
+// checks the hand against the table, printing the reason if it is invalid.
+fn unseen_tiles(hand: &TileSet, table: &TableState, is_text: bool) -> Result<TileSet, String> {
+    let wall = check_hand(hand, table, is_text);
+    if let (Err(e), true) = (&wall, is_text) {
+        println!("  {}.", e);
+    }
+    wall
+}
 
-    let n_samples = 1 << 21;
-    for i in 0..2 {
-        let (_, mut discards) = playout::discard_tile_parallel(hand, &mut wall, i, n_samples);
+fn check_hand(hand: &TileSet, table: &TableState, is_text: bool) -> Result<TileSet, String> {
+    if is_text {
+        println!("Hand: {}", format_tile_set(hand));
+    }
+    match table.visible() {
+        Ok(ref v) if v.is_empty() => (),
+        Ok(v) if is_text => println!("Visible: {}", format_tile_set(&v)),
+        Ok(_) => (),
+        Err(e) => return Err(format!("Invalid visible tiles: {}", e)),
+    }
+    if hand.count().is_multiple_of(3) {
+        return Err("# of tiles must be 3n + 1 or 3n + 2".to_string());
+    }
+    table.wall(hand).map_err(|e| format!("Invalid hand: {}", e))
+}
+
+// analyzes the hand by every engine of `params`, printing the results if `format` is the text.
+pub fn analyze_hand<R: rand::Rng>(
+    hand: &mut TileSet,
+    table: &TableState,
+    params: &Params,
+    format: Format,
+    rng: &mut R,
+) -> Result<Analysis, String> {
+    let start = time::Instant::now();
+    let is_text = format == Format::Text;
+    let mut wall = unseen_tiles(hand, table, is_text)?;
+    let n_live = table.n_live(&wall);
+    let n_draws = table.n_draws(&wall);
+    if is_text {
+        println!("  # of unseen tiles = {}, live wall = {}", wall.count(), n_live);
+    }
+
+    let mut sections = Vec::new();
+    if hand.count() % 3 == 1 {
+        sections = analyze_draw(hand, &mut wall, n_draws, table, params, is_text, rng);
+    } else {
+        if params.uses("search") {
+            sections.extend(analyze_search(hand, &mut wall, params, is_text));
+        }
+        sections.extend(analyze_danger(hand, table, is_text));
+        if params.uses("playout") {
+            sections.extend(analyze_playout(hand, &mut wall, n_draws, table, params, is_text, rng));
+        }
+        if params.uses("uct") {
+            sections.push(analyze_uct(hand, &wall, table, params, is_text, rng));
+        }
+    }
+
+    if is_text {
+        println!();
+    }
+    Ok(Analysis {
+        hand: hand.clone(),
+        wall,
+        n_live,
+        params: params.clone(),
+        sections,
+        elapsed: start.elapsed(),
+    })
+}
+
+fn analyze_search(hand: &mut TileSet, wall: &mut TileSet, params: &Params, is_text: bool) -> Vec<Section> {
+    let start = time::Instant::now();
+    let (score, _) = search::discard_tile(hand, wall, 0);
+    if is_text {
+        println!("  min-mean # of non-meld tiles, depth = 0:");
+        println!("       {:>11.8}", score);
+    }
+    let mut dst = vec![section(
+        "search",
+        Some(0),
+        None,
+        start,
+        vec![choice(None, vec![estimate("non_melds", score)])],
+    )];
+    for i in 1..=params.depth {
+        let start = time::Instant::now();
+        let (_, mut discards) = search::discard_tile_parallel(hand, wall, i);
         discards.sort_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap());
+        if is_text {
+            println!("  min-mean # of non-meld tiles, depth = {}:", i);
+            for (tile, score) in discards.iter() {
+                println!("    {} {:>11.8}", format_tile(*tile), score);
+            }
+        }
+        let choices = discards
+            .iter()
+            .map(|(t, s)| choice(Some(*t), vec![estimate("non_melds", *s)]))
+            .collect();
+        dst.push(section("search", Some(i), None, start, choices));
+    }
+    dst
+}
+
+fn analyze_playout<R: rand::Rng>(
+    hand: &mut TileSet,
+    wall: &mut TileSet,
+    n_draws: usize,
+    table: &TableState,
+    params: &Params,
+    is_text: bool,
+    rng: &mut R,
+) -> Vec<Section> {
+    let n_samples = params.n_samples;
+    let mut dst = Vec::new();
+    for i in 0..=params.playout_depth {
+        let start = time::Instant::now();
+        // the deeper playouts take the best of the means, so only the shallowest one is in batches.
+        let mut discards: Vec<_> = if i == 0 {
+            batch_means(n_samples, |n| {
+                let (_, discards) = playout::discard_tile_parallel(hand, wall, n_draws, i, n, rng.gen());
+                discards.into_iter().map(|(t, s)| (t, vec![s])).collect()
+            })
+            .into_iter()
+            .map(|(t, s)| (t, s[0].0, Some(s[0].1)))
+            .collect()
+        } else {
+            let (_, discards) = playout::discard_tile_parallel(hand, wall, n_draws, i, n_samples, rng.gen());
+            discards.into_iter().map(|(t, s)| (t, s, None)).collect()
+        };
+        discards.sort_by(|(_, s0, _), (_, s1, _)| s0.partial_cmp(s1).unwrap());
+        if is_text {
+            println!(
+                "  min-mean # of turns to win by playout, depth = {}, n_samples = {}:",
+                i, n_samples
+            );
+            for (tile, score, _) in discards.iter() {
+                println!("    {} {:>5.2}", format_tile(*tile), score);
+            }
+        }
+        let choices = discards
+            .into_iter()
+            .map(|(t, value, std_err)| {
+                let e = Estimate {
+                    name: "turns",
+                    value,
+                    std_err,
+                };
+                choice(Some(t), vec![e])
+            })
+            .collect();
+        dst.push(section("playout", Some(i), Some(n_samples), start, choices));
+    }
+
+    let start = time::Instant::now();
+    let ctx = table.win_context(WinType::Tsumo);
+    let horizon = danger::horizon(table, wall);
+    let mut discards = batch_means(n_samples, |n| {
+        let discards = playout::discard_tile_value_parallel(hand, &[], wall, &horizon, &ctx, n, rng.gen());
+        discards.into_iter().map(|(t, r, p)| (t, vec![r, p])).collect()
+    });
+    discards.sort_by(|(_, s0), (_, s1)| s1[1].0.partial_cmp(&s0[1].0).unwrap());
+    if is_text {
+        println!(
+            "  win rate and max-mean points by playout, n_draws = {}, end rate = {:.3}, n_samples = {}:",
+            horizon.n_draws, horizon.end_rate, n_samples
+        );
+        for (tile, s) in discards.iter() {
+            println!("    {} {:>5.3} {:>8.1}", format_tile(*tile), s[0].0, s[1].0);
+        }
+    }
+    let choices = discards
+        .into_iter()
+        .map(|(t, s)| choice(Some(t), vec![sampled("win_rate", s[0]), sampled("points", s[1])]))
+        .collect();
+    dst.push(section("value", None, Some(n_samples), start, choices));
+
+    let start = time::Instant::now();
+    let discards = batch_means(n_samples, |n| {
+        let discards = playout::discard_tile_riichi_parallel(hand, &[], wall, &horizon, &ctx, n, rng.gen());
+        discards
+            .into_iter()
+            .filter_map(|(t, c)| {
+                let (r0, p0) = c.riichi?;
+                let (r1, p1) = c.dama;
+                let (r2, p2) = c.improve;
+                Some((t, vec![r0, p0, r1, p1, r2, p2]))
+            })
+            .collect()
+    });
+    if !discards.is_empty() {
+        if is_text {
+            println!(
+                "  win rate and mean points of riichi | dama | improving by playout, n_draws = {}, n_samples = {}:",
+                horizon.n_draws, n_samples
+            );
+            for (tile, s) in discards.iter() {
+                println!(
+                    "    {} {:>5.3} {:>8.1} | {:>5.3} {:>8.1} | {:>5.3} {:>8.1}",
+                    format_tile(*tile),
+                    s[0].0,
+                    s[1].0,
+                    s[2].0,
+                    s[3].0,
+                    s[4].0,
+                    s[5].0
+                );
+            }
+        }
+        let names = [
+            "riichi_win_rate",
+            "riichi_points",
+            "dama_win_rate",
+            "dama_points",
+            "improve_win_rate",
+            "improve_points",
+        ];
+        let choices = discards
+            .into_iter()
+            .map(|(t, s)| choice(Some(t), names.iter().zip(s).map(|(n, e)| sampled(n, e)).collect()))
+            .collect();
+        dst.push(section("riichi", None, Some(n_samples), start, choices));
+    }
+
+    let start = time::Instant::now();
+    if let Ok(discards) = danger::push_fold(hand, &[], table, n_samples, rng.gen()) {
+        if discards.iter().any(|d| d.deal_in > 0.0) {
+            if is_text {
+                println!("  expected score delta by push / fold, with win rate, points, deal-in rate and loss:");
+                for d in discards.iter() {
+                    println!(
+                        "    {} {:>8.1} {:>4} {:>5.3} {:>8.1} {:>5.3} {:>8.1}",
+                        format_tile(d.tile),
+                        d.delta,
+                        if d.push { "push" } else { "fold" },
+                        d.win_rate,
+                        d.points,
+                        d.deal_in,
+                        d.loss
+                    );
+                }
+            }
+            let choices = discards
+                .iter()
+                .map(|d| {
+                    let estimates = vec![
+                        estimate("delta", d.delta),
+                        estimate("push", if d.push { 1.0 } else { 0.0 }),
+                        estimate("win_rate", d.win_rate),
+                        estimate("points", d.points),
+                        estimate("deal_in", d.deal_in),
+                        estimate("loss", d.loss),
+                    ];
+                    choice(Some(d.tile), estimates)
+                })
+                .collect();
+            dst.push(section("push_fold", None, Some(n_samples), start, choices));
+        }
+    }
+    dst
+}
+
+fn analyze_uct<R: rand::Rng>(
+    hand: &TileSet,
+    wall: &TileSet,
+    table: &TableState,
+    params: &Params,
+    is_text: bool,
+    rng: &mut R,
+) -> Section {
+    let start = time::Instant::now();
+    let ctx = table.win_context(WinType::Tsumo);
+    let horizon = danger::horizon(table, wall);
+    let mut discards = uct::discard_tile(hand, &[], wall, &horizon, &ctx, params.n_samples, rng);
+    discards.sort_by_key(|a| cmp::Reverse(a.count));
+    if is_text {
+        print_uct(&discards, params.n_samples);
+    }
+    // the tree spends the samples unevenly, so the # of visits stands for the confidence.
+    let choices = discards
+        .iter()
+        .map(|a| Choice {
+            tile: Some(a.tile),
+            riichi: a.riichi,
+            estimates: vec![
+                estimate("count", a.count as f64),
+                estimate("turns", a.turns),
+                estimate("points", a.points),
+            ],
+        })
+        .collect();
+    section("uct", None, Some(params.n_samples), start, choices)
+}
+
+// the discards of UCT, from the most visited.
+pub(crate) fn print_uct(discards: &[uct::Action], n_samples: usize) {
+    println!(
+        "  min-mean # of turns to win and mean points by UCT (* = riichi), n_samples = {}:",
+        n_samples
+    );
+    for a in discards.iter() {
+        let riichi = if a.riichi { "*" } else { " " };
         println!(
-            "  min-mean # of turns to win by playout, depth = {}, n_samples = {}:",
-            i, n_samples
+            "    {}{} {:>5.2} {:>8.1}",
+            format_tile(a.tile),
+            riichi,
+            a.turns,
+            a.points
         );
-        for (tile, score) in discards {
-            println!("    {} {:>5.2}", format_tile(tile), score);
+    }
+}
+
+fn analyze_draw<R: rand::Rng>(
+    hand: &mut TileSet,
+    wall: &mut TileSet,
+    n_draws: usize,
+    table: &TableState,
+    params: &Params,
+    is_text: bool,
+    rng: &mut R,
+) -> Vec<Section> {
+    let start = time::Instant::now();
+    let c = analyze_ukeire(hand, wall, is_text);
+    let mut dst = vec![section("ukeire", None, None, start, vec![c])];
+
+    if params.uses("search") {
+        for i in 0..params.depth {
+            let start = time::Instant::now();
+            let score = search::draw_tile(hand, wall, i);
+            if is_text {
+                println!("  mean # of non-meld tiles after the draw, depth = {}:", i);
+                println!("       {:>11.8}", score);
+            }
+            let c = choice(None, vec![estimate("non_melds", score)]);
+            dst.push(section("search", Some(i), None, start, vec![c]));
         }
     }
 
-    let mut discards = uct::discard_tile(hand, &wall, n_samples, &mut rand::thread_rng());
-    discards.sort_by(|(_, s0, _), (_, s1, _)| s1.cmp(s0));
-    println!("  min-mean # of turns to win by UCT, n_samples = {}:", n_samples);
-    for (tile, _, score) in discards {
-        println!("    {} {:>5.2}", format_tile(tile), score);
+    if params.uses("playout") {
+        let n_samples = params.n_samples;
+        for i in 0..=params.playout_depth {
+            let start = time::Instant::now();
+            let e = if i == 0 {
+                let means = batch_means(n_samples, |n| {
+                    vec![((), vec![playout::draw_tile(hand, wall, n_draws, i, n, rng)])]
+                });
+                let (score, std_err) = means[0].1[0];
+                sampled("turns", (score + 1.0, std_err))
+            } else {
+                estimate(
+                    "turns",
+                    playout::draw_tile(hand, wall, n_draws, i, n_samples, rng) + 1.0,
+                )
+            };
+            if is_text {
+                println!(
+                    "  mean # of turns to win by playout, depth = {}, n_samples = {}:",
+                    i, n_samples
+                );
+                println!("       {:>5.2}", e.value);
+            }
+            dst.push(section(
+                "playout",
+                Some(i),
+                Some(n_samples),
+                start,
+                vec![choice(None, vec![e])],
+            ));
+        }
     }
 
+    let start = time::Instant::now();
+    let choices = analyze_waits(hand, wall, table, is_text);
+    if !choices.is_empty() {
+        dst.push(section("waits", None, None, start, choices));
+    }
+    dst
+}
+
+fn analyze_ukeire(hand: &TileSet, wall: &TileSet, is_text: bool) -> Choice {
+    let tiles: Vec<_> = shanten::ukeire(hand, wall);
+    let n_ukeire: usize = tiles.iter().map(|(_, n)| n).sum();
+    let n_shanten = shanten::shanten(hand);
+    if is_text {
+        let names: Vec<_> = tiles.iter().map(|(t, _)| format_tile(*t)).collect();
+        println!("  shanten = {}, ukeire = {}: {}", n_shanten, n_ukeire, names.join(" "));
+    }
+    choice(
+        None,
+        vec![
+            estimate("shanten", n_shanten as f64),
+            estimate("ukeire", n_ukeire as f64),
+        ],
+    )
+}
+
+// the shanten number and ukeire of a hand of 3n + 1 tiles, or of each discard of a hand of 3n + 2 tiles.
+pub fn analyze_shanten(hand: &TileSet, table: &TableState) {
+    let wall = match unseen_tiles(hand, table, true) {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+    if hand.count() % 3 == 1 {
+        analyze_ukeire(hand, &wall, true);
+    } else {
+        println!("  shanten = {}", shanten::shanten(hand));
+        let mut hand = hand.clone();
+        for i in 0..hand.len() {
+            if hand.tile(i) == 0 {
+                continue;
+            }
+            *hand.tile_mut(i) -= 1;
+            let tiles = shanten::ukeire(&hand, &wall);
+            let n_ukeire: usize = tiles.iter().map(|(_, n)| n).sum();
+            println!(
+                "    {} shanten = {}, ukeire = {:>2}",
+                format_tile(i),
+                shanten::shanten(&hand),
+                n_ukeire
+            );
+            *hand.tile_mut(i) += 1;
+        }
+    }
     println!();
 }
 
-pub fn benchmark<R: rand::Rng>(rng: &mut R) {
-    let discard_funcs: Vec<(_, &dyn Fn(&mut TileSet, &mut TileSet) -> usize)> = vec![
-        ("search", &|hand: &mut TileSet, wall: &mut TileSet| {
-            let (_, discards) = search::discard_tile_parallel(hand, wall, 3);
-            let (tile, _) = discards
+// the waits of a hand of 3n + 1 tiles.
+pub fn analyze_wait_tiles(hand: &TileSet, table: &TableState) {
+    let wall = match unseen_tiles(hand, table, true) {
+        Ok(w) => w,
+        Err(_) => return,
+    };
+    if hand.count() % 3 != 1 {
+        println!("  # of tiles must be 3n + 1.");
+    } else if wait::winning_tiles(hand, &wall).is_empty() {
+        println!("  not in tenpai, shanten = {}", shanten::shanten(hand));
+    } else {
+        analyze_waits(hand, &wall, table, true);
+    }
+    println!();
+}
+
+// the live tiles and points of each winning tile.
+fn analyze_waits(hand: &TileSet, wall: &TileSet, table: &TableState, is_text: bool) -> Vec<Choice> {
+    let tiles = wait::winning_tiles(hand, wall);
+    if tiles.is_empty() {
+        return Vec::new();
+    }
+    let n_live: usize = tiles.iter().map(|w| w.n_live).sum();
+    let furiten = if wait::is_furiten(&tiles, &table.own_discards) {
+        ", furiten"
+    } else {
+        ""
+    };
+    if is_text {
+        println!("  waits, {} live tiles{}, points by tsumo / ron:", n_live, furiten);
+    }
+    let mut dst = Vec::new();
+    for w in tiles.iter() {
+        let mut hand = hand.clone();
+        *hand.tile_mut(w.tile) += 1;
+        let points: Vec<_> = [WinType::Tsumo, WinType::Ron]
+            .iter()
+            .map(|t| score_hand(&hand, &[], w.tile, &table.win_context(*t)).map(|s| s.points))
+            .collect();
+        if is_text {
+            let texts: Vec<_> = points
                 .iter()
-                .min_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap())
-                .unwrap();
-            *tile
-        }),
-        ("playout", &|hand: &mut TileSet, wall: &mut TileSet| {
-            let (_, discards) = playout::discard_tile_parallel(hand, wall, 1, 1 << 21);
-            let (tile, _) = discards
+                .map(|p| match p {
+                    Some(p) => format!("{:>5}", p),
+                    None => format!("{:>5}", "-"),
+                })
+                .collect();
+            let shapes: Vec<_> = w.shapes.iter().map(|s| wait::format_wait(&tiles, w, *s)).collect();
+            println!(
+                "    {} {:>2} {} {}",
+                format_tile(w.tile),
+                w.n_live,
+                texts.join(" "),
+                shapes.join(", ")
+            );
+        }
+        // a wait without yaku has no points.
+        let estimates = vec![
+            estimate("n_live", w.n_live as f64),
+            estimate("tsumo_points", points[0].map_or(f64::NAN, |p| p as f64)),
+            estimate("ron_points", points[1].map_or(f64::NAN, |p| p as f64)),
+        ];
+        dst.push(choice(Some(w.tile), estimates));
+    }
+    dst
+}
+
+fn analyze_danger(hand: &TileSet, table: &TableState, is_text: bool) -> Option<Section> {
+    if table.opponents.iter().all(|o| o.discards.is_empty() && !o.riichi) {
+        return None;
+    }
+    let start = time::Instant::now();
+    let mut discards = danger::discard_danger(hand, table).ok()?;
+    discards.sort_by(|d0, d1| d0.deal_in.partial_cmp(&d1.deal_in).unwrap());
+    if is_text {
+        println!("  deal-in rate, and safety and rate in tenpai vs. shimocha | toimen | kamicha:");
+        for d in discards.iter() {
+            let cols: Vec<_> = d
+                .safety
                 .iter()
-                .min_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap())
-                .unwrap();
-            *tile
-        }),
-        ("uct", &|hand: &mut TileSet, wall: &mut TileSet| {
-            let discards = uct::discard_tile(&hand, &wall, 1 << 19, &mut rand::thread_rng());
-            let (tile, _, _) = discards.iter().max_by_key(|(_, s, _)| s).unwrap();
-            *tile
-        }),
+                .zip(d.rates.iter())
+                .map(|(s, r)| format!("{:>10} {:>5.3}", s.name(), r))
+                .collect();
+            println!("    {} {:>5.3} {}", format_tile(d.tile), d.deal_in, cols.join(" | "));
+        }
+    }
+    let names = ["shimocha_rate", "toimen_rate", "kamicha_rate"];
+    let choices = discards
+        .iter()
+        .map(|d| {
+            let mut estimates = vec![estimate("deal_in", d.deal_in)];
+            estimates.extend(names.iter().zip(d.rates.iter()).map(|(n, r)| estimate(n, *r)));
+            choice(Some(d.tile), estimates)
+        })
+        .collect();
+    Some(section("danger", None, None, start, choices))
+}
+
+fn format_hand(hand: &TileSet) -> String {
+    format_tile_set(hand).trim_end().to_string()
+}
+
+// the analysis as a json object, with an object of the estimates and their errors for each choice.
+pub fn to_json(a: &Analysis) -> Value {
+    let sections: Vec<_> = a
+        .sections
+        .iter()
+        .map(|s| {
+            let choices: Vec<_> = s
+                .choices
+                .iter()
+                .map(|c| {
+                    let mut dst = serde_json::Map::new();
+                    dst.insert("tile".to_string(), json!(c.tile.map(format_tile)));
+                    dst.insert("riichi".to_string(), json!(c.riichi));
+                    for e in c.estimates.iter() {
+                        dst.insert(e.name.to_string(), json!(e.value));
+                        dst.insert(format!("{}_std_err", e.name), json!(e.std_err));
+                    }
+                    Value::Object(dst)
+                })
+                .collect();
+            json!({
+                "engine": s.engine,
+                "depth": s.depth,
+                "n_samples": s.n_samples,
+                "seconds": s.elapsed.as_secs_f64(),
+                "choices": choices,
+            })
+        })
+        .collect();
+    json!({
+        "hand": format_hand(&a.hand),
+        "wall": format_hand(&a.wall),
+        "n_unseen": a.wall.count(),
+        "n_live": a.n_live,
+        "params": {
+            "depth": a.params.depth,
+            "playout_depth": a.params.playout_depth,
+            "n_samples": a.params.n_samples,
+            "engine": a.params.engine,
+        },
+        "seconds": a.elapsed.as_secs_f64(),
+        "sections": sections,
+    })
+}
+
+pub const CSV_HEADER: &str = "hand,wall,n_live,engine,depth,n_samples,seconds,tile,riichi,estimate,value,std_err\n";
+
+// the analysis as the csv rows of each estimate, without the header.  the missing values are empty.
+pub fn to_csv(a: &Analysis) -> String {
+    fn field<T: fmt::Display>(v: Option<T>) -> String {
+        v.map_or(String::new(), |v| v.to_string())
+    }
+    let mut dst = String::new();
+    for s in a.sections.iter() {
+        for c in s.choices.iter() {
+            for e in c.estimates.iter() {
+                dst += &format!(
+                    "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                    format_hand(&a.hand),
+                    format_hand(&a.wall),
+                    a.n_live,
+                    s.engine,
+                    field(s.depth),
+                    field(s.n_samples),
+                    s.elapsed.as_secs_f64(),
+                    field(c.tile.map(format_tile)),
+                    c.riichi,
+                    e.name,
+                    field(Some(e.value).filter(|v| v.is_finite())),
+                    field(e.std_err),
+                );
+            }
+        }
+    }
+    dst
+}
+
+type DiscardFn<'a> = Box<dyn Fn(&mut TileSet, &mut TileSet) -> usize + 'a>;
+
+pub fn benchmark<R: rand::Rng>(params: &Params, rng: &mut R) {
+    let depth = params.depth;
+    let playout_depth = params.playout_depth;
+    let n_samples = params.n_samples;
+    let playout_rng = cell::RefCell::new(rand::rngs::StdRng::seed_from_u64(rng.gen()));
+    let uct_rng = cell::RefCell::new(rand::rngs::StdRng::seed_from_u64(rng.gen()));
+    let discard_funcs: Vec<(_, DiscardFn)> = vec![
+        (
+            "search",
+            Box::new(|hand: &mut TileSet, wall: &mut TileSet| {
+                let (_, discards) = search::discard_tile_parallel(hand, wall, depth);
+                let (tile, _) = discards
+                    .iter()
+                    .min_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap())
+                    .unwrap();
+                *tile
+            }),
+        ),
+        (
+            "playout",
+            Box::new(|hand: &mut TileSet, wall: &mut TileSet| {
+                let seed = rand::Rng::gen(&mut *playout_rng.borrow_mut());
+                let (_, discards) =
+                    playout::discard_tile_parallel(hand, wall, wall.count(), playout_depth, n_samples, seed);
+                let (tile, _) = discards
+                    .iter()
+                    .min_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap())
+                    .unwrap();
+                *tile
+            }),
+        ),
+        (
+            "uct",
+            Box::new(|hand: &mut TileSet, wall: &mut TileSet| {
+                let ctx = WinContext::new(WinType::Tsumo);
+                let rng = &mut *uct_rng.borrow_mut();
+                // UCT converges with fewer samples.
+                let discards = uct::discard_tile(
+                    hand,
+                    &[],
+                    wall,
+                    &playout::Horizon::new(wall.count()),
+                    &ctx,
+                    n_samples / 4,
+                    rng,
+                );
+                discards.iter().max_by_key(|a| a.count).unwrap().tile
+            }),
+        ),
     ];
+    let discard_funcs: Vec<_> = discard_funcs
+        .into_iter()
+        .filter(|(name, _)| params.uses(name))
+        .collect();
 
     let mut sums = vec![(0u64, 0u64); discard_funcs.len()];
     for n_samples in 1.. {
@@ -146,3 +884,34 @@ pub fn benchmark<R: rand::Rng>(rng: &mut R) {
         println!();
     }
 }
+
+// plays the engines against each other, with light settings to keep the games short.
+pub fn arena(n_games: usize, seed: u64) {
+    let mut ukeire = strategy::Ukeire;
+    let mut search = strategy::Search { depth: 1 };
+    let mut playout = strategy::Playout {
+        depth: 0,
+        n_samples: 1 << 12,
+    };
+    let mut uct = strategy::Uct { n_samples: 1 << 12 };
+    let names = ["ukeire", "search", "playout", "uct"];
+    let stats = arena::run(
+        &GameConfig::default(),
+        &mut [&mut ukeire, &mut search, &mut playout, &mut uct],
+        n_games,
+        seed,
+    );
+
+    println!("N = {}, seed = {}", n_games, seed);
+    println!("{:>7}  rank  win rate  deal-in rate   score", "");
+    for (name, s) in names.iter().zip(stats.iter()) {
+        println!(
+            "{:>7}: {:>4.2} {:>9.3} {:>13.3} {:>7.0}",
+            name,
+            s.mean_rank(),
+            s.win_rate(),
+            s.deal_in_rate(),
+            s.mean_score()
+        );
+    }
+}
@@ -0,0 +1,271 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+// the C API of the cdylib, declared in include/teff.h, which is generated by cbindgen.  the pointers passed in must
+// be valid or null where noted, and the tile sets are owned by the caller once created.
+#![allow(clippy::missing_safety_doc)]
+use crate::danger;
+use crate::playout;
+use crate::score::*;
+use crate::search;
+use crate::shanten;
+use crate::table::*;
+use crate::tile::*;
+use crate::uct;
+use rand::SeedableRng;
+use std::convert::TryFrom;
+use std::os::raw::c_char;
+use std::*;
+
+pub const TEFF_N_KINDS: usize = 34;
+
+pub const TEFF_OK: i32 = 0;
+pub const TEFF_ERROR_NULL: i32 = -1;
+// a tile index out of 0..34, or more than 4 copies of a tile.
+pub const TEFF_ERROR_TILE: i32 = -2;
+// a hand of 3n tiles, or one which does not fit the visible tiles.
+pub const TEFF_ERROR_HAND: i32 = -3;
+pub const TEFF_ERROR_ENGINE: i32 = -4;
+
+pub const TEFF_ENGINE_SEARCH: i32 = 0;
+pub const TEFF_ENGINE_PLAYOUT: i32 = 1;
+pub const TEFF_ENGINE_UCT: i32 = 2;
+
+// a set of tiles, by the # of each of the 34 kinds.
+pub struct TeffTileSet(TileSet);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct TeffConfig {
+    pub engine: i32,
+    // the depth of the search or the playouts.
+    pub depth: usize,
+    // # of samples of the playouts and UCT.
+    pub n_samples: usize,
+    // the seed of the playouts and UCT.
+    pub seed: u64,
+}
+
+// the result of a discard, from the best.  `score` is the min-mean # of non-meld tiles of the search, or the mean #
+// of turns to win of the playouts and UCT; lower is better.  `points` and `count`, the # of visits, are of UCT.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct TeffDiscard {
+    pub tile: i32,
+    pub riichi: bool,
+    pub score: f64,
+    pub points: f64,
+    pub count: usize,
+}
+
+#[no_mangle]
+pub extern "C" fn teff_tile_set_new() -> *mut TeffTileSet {
+    Box::into_raw(Box::new(TeffTileSet(TileSet::new())))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn teff_tile_set_free(set: *mut TeffTileSet) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn teff_tile_set_clone(set: *const TeffTileSet) -> *mut TeffTileSet {
+    match set.as_ref() {
+        Some(s) => Box::into_raw(Box::new(TeffTileSet(s.0.clone()))),
+        None => ptr::null_mut(),
+    }
+}
+
+// the # of copies of `tile`, or a negative error.
+#[no_mangle]
+pub unsafe extern "C" fn teff_tile_set_get(set: *const TeffTileSet, tile: i32) -> i32 {
+    match (set.as_ref(), usize::try_from(tile)) {
+        (None, _) => TEFF_ERROR_NULL,
+        (Some(s), Ok(t)) if t < N_KINDS => s.0.tile(t) as i32,
+        _ => TEFF_ERROR_TILE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn teff_tile_set_set(set: *mut TeffTileSet, tile: i32, n: i32) -> i32 {
+    match (set.as_mut(), usize::try_from(tile)) {
+        (None, _) => TEFF_ERROR_NULL,
+        (Some(s), Ok(t)) if t < N_KINDS && (0..=N_COPIES as i32).contains(&n) => {
+            *s.0.tile_mut(t) = n as u8;
+            TEFF_OK
+        }
+        _ => TEFF_ERROR_TILE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn teff_tile_set_count(set: *const TeffTileSet) -> usize {
+    set.as_ref().map_or(0, |s| s.0.count())
+}
+
+// parses the notation, e.g. "123m456p789s1122z", or returns null with the position of the error in `error_pos` if it
+// is not null.
+#[no_mangle]
+pub unsafe extern "C" fn teff_tile_set_parse(text: *const c_char, error_pos: *mut usize) -> *mut TeffTileSet {
+    if text.is_null() {
+        return ptr::null_mut();
+    }
+    let text = ffi::CStr::from_ptr(text).to_string_lossy();
+    match parse_tile_set(&text) {
+        Ok(t) => Box::into_raw(Box::new(TeffTileSet(t))),
+        Err(e) => {
+            if let Some(p) = error_pos.as_mut() {
+                *p = e.pos;
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+// writes the notation of the set, terminated by nul, into `buf` of `size` bytes, like snprintf().  returns the
+// length of the notation, which is truncated if it is not less than `size`.
+#[no_mangle]
+pub unsafe extern "C" fn teff_tile_set_format(set: *const TeffTileSet, buf: *mut c_char, size: usize) -> usize {
+    let text = match set.as_ref() {
+        Some(s) => format_tile_set(&s.0).trim_end().to_string(),
+        None => return 0,
+    };
+    if !buf.is_null() && size > 0 {
+        let n = cmp::min(text.len(), size - 1);
+        ptr::copy_nonoverlapping(text.as_ptr(), buf as *mut u8, n);
+        *buf.add(n) = 0;
+    }
+    text.len()
+}
+
+fn unseen(hand: &TileSet, visible: Option<&TeffTileSet>) -> Option<(TileSet, TableState)> {
+    let mut table = TableState::new();
+    if let Some(v) = visible {
+        table.discards = v.0.clone();
+    }
+    if hand.validate().is_err() || hand.count().is_multiple_of(3) {
+        return None;
+    }
+    let wall = table.wall(hand).ok()?;
+    Some((wall, table))
+}
+
+// the shanten number of a hand of 3n + 1 or 3n + 2 tiles, which is -1 if it is complete, or a negative error below
+// that.
+#[no_mangle]
+pub unsafe extern "C" fn teff_shanten(hand: *const TeffTileSet) -> i32 {
+    match hand.as_ref() {
+        Some(h) if h.0.validate().is_ok() && !h.0.count().is_multiple_of(3) => shanten::shanten(&h.0) as i32,
+        Some(_) => TEFF_ERROR_HAND,
+        None => TEFF_ERROR_NULL,
+    }
+}
+
+// the # of the tiles which lower the shanten number of a hand of 3n + 1 tiles, out of the tiles neither in the hand
+// nor in `visible`, which may be null.  `counts`, if not null, gets the # of each of the 34 kinds.
+#[no_mangle]
+pub unsafe extern "C" fn teff_ukeire(hand: *const TeffTileSet, visible: *const TeffTileSet, counts: *mut usize) -> i32 {
+    let hand = match hand.as_ref() {
+        Some(h) => &h.0,
+        None => return TEFF_ERROR_NULL,
+    };
+    let wall = match unseen(hand, visible.as_ref()) {
+        Some((w, _)) if hand.count() % 3 == 1 => w,
+        _ => return TEFF_ERROR_HAND,
+    };
+    let tiles = shanten::ukeire(hand, &wall);
+    if !counts.is_null() {
+        let counts = slice::from_raw_parts_mut(counts, N_KINDS);
+        counts.fill(0);
+        for (t, n) in tiles.iter() {
+            counts[*t] = *n;
+        }
+    }
+    tiles.iter().map(|(_, n)| n).sum::<usize>() as i32
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn teff_config_default(config: *mut TeffConfig) {
+    if let Some(c) = config.as_mut() {
+        *c = TeffConfig {
+            engine: TEFF_ENGINE_SEARCH,
+            depth: 2,
+            n_samples: 1 << 16,
+            seed: 0,
+        };
+    }
+}
+
+// analyzes the discards of a hand of 3n + 2 tiles, with the tiles in `visible`, which may be null, out of the wall.
+// writes the results from the best into `results` of `capacity` elements and returns the # of the results, which
+// are truncated if it is more than `capacity`, or a negative error.  the results are empty for a complete hand.
+#[no_mangle]
+pub unsafe extern "C" fn teff_analyze_discards(
+    hand: *const TeffTileSet,
+    visible: *const TeffTileSet,
+    config: *const TeffConfig,
+    results: *mut TeffDiscard,
+    capacity: usize,
+) -> i32 {
+    let (hand, config) = match (hand.as_ref(), config.as_ref()) {
+        (Some(h), Some(c)) => (&h.0, c),
+        _ => return TEFF_ERROR_NULL,
+    };
+    let (mut wall, table) = match unseen(hand, visible.as_ref()) {
+        Some(w) if hand.count() % 3 == 2 => w,
+        _ => return TEFF_ERROR_HAND,
+    };
+    let horizon = danger::horizon(&table, &wall);
+    let mut hand = hand.clone();
+
+    let discard = |tile: usize, score: f64| TeffDiscard {
+        tile: tile as i32,
+        riichi: false,
+        score,
+        points: 0.0,
+        count: 0,
+    };
+    let mut dst: Vec<_> = match config.engine {
+        TEFF_ENGINE_SEARCH => {
+            let (_, scores) = search::discard_tile_parallel(&mut hand, &mut wall, config.depth);
+            scores.into_iter().map(|(t, s)| discard(t, s)).collect()
+        }
+        TEFF_ENGINE_PLAYOUT => {
+            let (_, scores) = playout::discard_tile_parallel(
+                &mut hand,
+                &mut wall,
+                horizon.n_draws,
+                config.depth,
+                config.n_samples,
+                config.seed,
+            );
+            scores.into_iter().map(|(t, s)| discard(t, s)).collect()
+        }
+        TEFF_ENGINE_UCT => {
+            let ctx = table.win_context(WinType::Tsumo);
+            let mut rng = rand::rngs::StdRng::seed_from_u64(config.seed);
+            let actions = uct::discard_tile(&hand, &[], &wall, &horizon, &ctx, config.n_samples, &mut rng);
+            actions
+                .into_iter()
+                .map(|a| TeffDiscard {
+                    riichi: a.riichi,
+                    points: a.points,
+                    count: a.count,
+                    ..discard(a.tile, a.turns)
+                })
+                .collect()
+        }
+        _ => return TEFF_ERROR_ENGINE,
+    };
+    if config.engine == TEFF_ENGINE_UCT {
+        dst.sort_by_key(|d| cmp::Reverse(d.count));
+    } else {
+        dst.sort_by(|d0, d1| d0.score.partial_cmp(&d1.score).unwrap());
+    }
+
+    if !results.is_null() {
+        let n = cmp::min(dst.len(), capacity);
+        ptr::copy_nonoverlapping(dst.as_ptr(), results, n);
+    }
+    dst.len() as i32
+}
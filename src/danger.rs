@@ -0,0 +1,292 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::playout::{self, Horizon};
+use crate::score::*;
+use crate::table::*;
+use crate::tile::*;
+use std::*;
+
+// the relative frequency of each wait shape, i.e. the share of the shape in tenpai hands divided by the # of
+// shapes of the kind.
+const RYANMEN_WEIGHT: f64 = 3.3;
+const KANCHAN_WEIGHT: f64 = 0.75;
+const PENCHAN_WEIGHT: f64 = 0.75;
+const SHANPON_WEIGHT: f64 = 0.35;
+const TANKI_WEIGHT: f64 = 0.25;
+
+// the probability that an opponent in tenpai wins before each of our draws.
+const TENPAI_WIN_RATE: f64 = 0.08;
+// the mean points paid on a deal-in to a non-dealer, and to a non-dealer in riichi.
+const DEAL_IN_POINTS: f64 = 5200.0;
+const RIICHI_DEAL_IN_POINTS: f64 = 6400.0;
+
+// the safest reason a tile is hard to deal in with, from the safest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Safety {
+    // discarded by the opponent, i.e. furiten.
+    Genbutsu,
+    // every ryanmen on the tile needs a tile whose copies are all visible (kabe), or every copy of an honor is
+    // visible.
+    NoChance,
+    // every ryanmen on the tile is furiten.
+    Suji,
+    // ryanmen on the tile are possible only with the last unseen copy of a tile.
+    OneChance,
+    Unsafe,
+}
+
+impl Safety {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Safety::Genbutsu => "genbutsu",
+            Safety::NoChance => "no-chance",
+            Safety::Suji => "suji",
+            Safety::OneChance => "one-chance",
+            Safety::Unsafe => "-",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Danger {
+    pub tile: usize,
+    // for each opponent.
+    pub safety: Vec<Safety>,
+    // for each opponent, the probability that the tile wins the hand if the opponent is in tenpai.
+    pub rates: Vec<f64>,
+    // the probability that the tile deals in to any opponent.
+    pub deal_in: f64,
+}
+
+// a wait shape: (weight, # of copies held by the opponent for each tile, winning tiles).
+type Shape = (f64, Vec<(usize, usize)>, Vec<usize>);
+
+fn shapes() -> Vec<Shape> {
+    let mut dst = Vec::new();
+    for i in 0..27 {
+        let n = i % 9;
+        if (1..7).contains(&n) {
+            dst.push((RYANMEN_WEIGHT, vec![(i, 1), (i + 1, 1)], vec![i - 1, i + 2]));
+        }
+        if n == 0 {
+            dst.push((PENCHAN_WEIGHT, vec![(i, 1), (i + 1, 1)], vec![i + 2]));
+        }
+        if n == 7 {
+            dst.push((PENCHAN_WEIGHT, vec![(i, 1), (i + 1, 1)], vec![i - 1]));
+        }
+        if n < 7 {
+            dst.push((KANCHAN_WEIGHT, vec![(i, 1), (i + 2, 1)], vec![i + 1]));
+        }
+    }
+    for i in 0..N_KINDS {
+        dst.push((SHANPON_WEIGHT, vec![(i, 2)], vec![i]));
+        dst.push((TANKI_WEIGHT, vec![(i, 1)], vec![i]));
+    }
+    dst
+}
+
+fn choose(n: usize, k: usize) -> f64 {
+    if n < k {
+        return 0.0;
+    }
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+// the probability that each tile wins the hand of `opponent` in tenpai.  the shapes are weighted by the # of ways
+// to hold their tiles out of the unseen tiles `wall`, and the furiten shapes are excluded.
+pub fn wait_rates(wall: &TileSet, opponent: &Opponent) -> [f64; N_KINDS] {
+    let mut weights = Vec::new();
+    for (weight, held, wins) in shapes() {
+        if wins.iter().any(|t| opponent.discards.tile(*t) > 0) {
+            continue;
+        }
+        let ways: f64 = held
+            .iter()
+            .map(|(t, k)| choose(wall.tile(*t) as usize, *k) / choose(N_COPIES as usize, *k))
+            .product();
+        weights.push((weight * ways, wins));
+    }
+
+    let total: f64 = weights.iter().map(|(w, _)| w).sum();
+    let mut dst = [0.0; N_KINDS];
+    if total <= 0.0 {
+        return dst;
+    }
+    for (w, wins) in weights {
+        for t in wins {
+            dst[t] += w / total;
+        }
+    }
+    dst
+}
+
+pub fn safety(tile: usize, wall: &TileSet, opponent: &Opponent) -> Safety {
+    if opponent.discards.tile(tile) > 0 {
+        return Safety::Genbutsu;
+    }
+    if is_honor(tile) {
+        return if wall.tile(tile) == 0 {
+            Safety::NoChance
+        } else {
+            Safety::Unsafe
+        };
+    }
+
+    // the ryanmen on `tile` as (held tiles, the other winning tile).
+    let n = tile % 9;
+    let mut ryanmen = Vec::new();
+    if n >= 3 {
+        ryanmen.push(([tile - 2, tile - 1], tile - 3));
+    }
+    if n <= 5 {
+        ryanmen.push(([tile + 1, tile + 2], tile + 3));
+    }
+    let is_suji = |other: usize| opponent.discards.tile(other) > 0;
+    let n_copies = |held: &[usize; 2]| held.iter().map(|t| wall.tile(*t)).min().unwrap();
+    if ryanmen.iter().all(|(_, other)| is_suji(*other)) {
+        Safety::Suji
+    } else if ryanmen
+        .iter()
+        .all(|(held, other)| is_suji(*other) || n_copies(held) == 0)
+    {
+        Safety::NoChance
+    } else if ryanmen
+        .iter()
+        .all(|(held, other)| is_suji(*other) || n_copies(held) <= 1)
+    {
+        Safety::OneChance
+    } else {
+        Safety::Unsafe
+    }
+}
+
+// a rough estimate from the # of discards.  an opponent in riichi is in tenpai.
+pub fn tenpai_rate(opponent: &Opponent) -> f64 {
+    if opponent.riichi {
+        return 1.0;
+    }
+    let n = opponent.discards.count() as f64 / 18.0;
+    f64::min(n * n, 1.0)
+}
+
+// the danger of each discard of `hand` against the opponents of `table`.
+pub fn discard_danger(hand: &TileSet, table: &TableState) -> Result<Vec<Danger>, TileError> {
+    let wall = table.wall(hand)?;
+    let rates: Vec<_> = table.opponents.iter().map(|o| wait_rates(&wall, o)).collect();
+    let mut dst = Vec::new();
+    for i in 0..hand.len() {
+        if hand.tile(i) == 0 {
+            continue;
+        }
+        let safe_rate: f64 = table
+            .opponents
+            .iter()
+            .zip(rates.iter())
+            .map(|(o, r)| 1.0 - tenpai_rate(o) * r[i])
+            .product();
+        dst.push(Danger {
+            tile: i,
+            safety: table.opponents.iter().map(|o| safety(i, &wall, o)).collect(),
+            rates: rates.iter().map(|r| r[i]).collect(),
+            deal_in: 1.0 - safe_rate,
+        });
+    }
+    Ok(dst)
+}
+
+// the probability that any opponent wins before each of our draws.
+pub fn round_end_rate(table: &TableState) -> f64 {
+    let rate: f64 = table
+        .opponents
+        .iter()
+        .map(|o| 1.0 - tenpai_rate(o) * TENPAI_WIN_RATE)
+        .product();
+    1.0 - rate
+}
+
+// our own draws left in the round, which the opponents can end.
+pub fn horizon(table: &TableState, wall: &TileSet) -> Horizon {
+    Horizon {
+        n_draws: table.n_draws(wall),
+        end_rate: round_end_rate(table),
+    }
+}
+
+// the mean points paid on a deal-in to the `i`-th opponent, counted from shimocha.
+pub fn deal_in_points(table: &TableState, i: usize) -> f64 {
+    let points = if table.opponents[i].riichi {
+        RIICHI_DEAL_IN_POINTS
+    } else {
+        DEAL_IN_POINTS
+    };
+    let is_dealer = (table.seat_wind + i + 1).is_multiple_of(4);
+    if is_dealer {
+        points * 1.5
+    } else {
+        points
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PushFold {
+    pub tile: usize,
+    // our win rate and mean points after the discard, if it does not deal in.
+    pub win_rate: f64,
+    pub points: f64,
+    pub deal_in: f64,
+    // the mean points lost by dealing in.
+    pub loss: f64,
+    // the expected change of our score, i.e. (1 - deal_in) * points - loss.
+    pub delta: f64,
+    // whether the discard is better than folding, i.e. discarding the safest tile.
+    pub push: bool,
+}
+
+// ranks the discards by the expected change of our score, combining our own playouts, where the opponents may win
+// first, with the danger of each discard.  only the danger of the discard itself is counted.  `seed` seeds the
+// playouts.
+pub fn push_fold(
+    hand: &TileSet,
+    melds: &[Meld],
+    table: &TableState,
+    n_samples: usize,
+    seed: u64,
+) -> Result<Vec<PushFold>, TileError> {
+    let mut wall = table.wall(hand)?;
+    let horizon = horizon(table, &wall);
+    let ctx = table.win_context(WinType::Tsumo);
+    let dangers = discard_danger(hand, table)?;
+    let values =
+        playout::discard_tile_value_parallel(&mut hand.clone(), melds, &mut wall, &horizon, &ctx, n_samples, seed);
+
+    let mut dst = Vec::new();
+    for d in dangers.iter() {
+        let (win_rate, points) = values
+            .iter()
+            .find(|(t, _, _)| *t == d.tile)
+            .map_or((0.0, 0.0), |(_, r, p)| (*r, *p));
+        let loss: f64 = (0..table.opponents.len())
+            .map(|i| tenpai_rate(&table.opponents[i]) * d.rates[i] * deal_in_points(table, i))
+            .sum();
+        dst.push(PushFold {
+            tile: d.tile,
+            win_rate,
+            points,
+            deal_in: d.deal_in,
+            loss,
+            delta: (1.0 - d.deal_in) * points - loss,
+            push: false,
+        });
+    }
+
+    let fold = dst
+        .iter()
+        .min_by(|a, b| (a.deal_in, -a.delta).partial_cmp(&(b.deal_in, -b.delta)).unwrap())
+        .map(|e| (e.tile, e.delta));
+    if let Some((tile, delta)) = fold {
+        for e in dst.iter_mut() {
+            e.push = e.tile != tile && e.delta > delta;
+        }
+    }
+    dst.sort_by(|a, b| b.delta.partial_cmp(&a.delta).unwrap());
+    Ok(dst)
+}
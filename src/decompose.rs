@@ -0,0 +1,198 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::tile::*;
+use std::*;
+
+// blocks are identified by their smallest tile.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Block {
+    Triplet(usize),
+    Sequence(usize),
+    Pair(usize),
+    // i, i + 1.
+    Adjacent(usize),
+    // i, i + 2.
+    Gapped(usize),
+    Single(usize),
+}
+
+impl Block {
+    pub fn tile(&self) -> usize {
+        match *self {
+            Block::Triplet(i)
+            | Block::Sequence(i)
+            | Block::Pair(i)
+            | Block::Adjacent(i)
+            | Block::Gapped(i)
+            | Block::Single(i) => i,
+        }
+    }
+
+    pub fn tiles(&self) -> TileSet {
+        let mut dst = TileSet::new();
+        let i = self.tile();
+        match *self {
+            Block::Triplet(_) => *dst.tile_mut(i) += 3,
+            Block::Sequence(_) => {
+                for j in i..i + 3 {
+                    *dst.tile_mut(j) += 1;
+                }
+            }
+            Block::Pair(_) => *dst.tile_mut(i) += 2,
+            Block::Adjacent(_) => {
+                *dst.tile_mut(i) += 1;
+                *dst.tile_mut(i + 1) += 1;
+            }
+            Block::Gapped(_) => {
+                *dst.tile_mut(i) += 1;
+                *dst.tile_mut(i + 2) += 1;
+            }
+            Block::Single(_) => *dst.tile_mut(i) += 1,
+        }
+        dst
+    }
+
+    pub fn is_triad(&self) -> bool {
+        matches!(*self, Block::Triplet(_) | Block::Sequence(_))
+    }
+
+    // the tiles which turn this partial block into a triad.
+    pub fn waits(&self) -> Vec<usize> {
+        match *self {
+            Block::Pair(i) => vec![i],
+            Block::Adjacent(i) => {
+                let mut dst = Vec::new();
+                if i % 9 > 0 {
+                    dst.push(i - 1);
+                }
+                if i % 9 < 7 {
+                    dst.push(i + 2);
+                }
+                dst
+            }
+            Block::Gapped(i) => vec![i + 1],
+            _ => Vec::new(),
+        }
+    }
+
+    fn kind(&self) -> usize {
+        match *self {
+            Block::Triplet(_) => 0,
+            Block::Sequence(_) => 1,
+            Block::Pair(_) => 2,
+            Block::Adjacent(_) => 3,
+            Block::Gapped(_) => 4,
+            Block::Single(_) => 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wait {
+    Ryanmen,
+    Kanchan,
+    Penchan,
+    Shanpon,
+    Tanki,
+}
+
+// the shape `block` of a complete hand waited in, if it was completed by `tile`.
+pub fn wait_shape(block: &Block, tile: usize) -> Option<Wait> {
+    match *block {
+        Block::Pair(i) if i == tile => Some(Wait::Tanki),
+        Block::Triplet(i) if i == tile => Some(Wait::Shanpon),
+        Block::Sequence(i) if i + 1 == tile => Some(Wait::Kanchan),
+        Block::Sequence(i) if i == tile && i % 9 == 6 => Some(Wait::Penchan),
+        Block::Sequence(i) if i + 2 == tile && i % 9 == 0 => Some(Wait::Penchan),
+        Block::Sequence(i) if i == tile || i + 2 == tile => Some(Wait::Ryanmen),
+        _ => None,
+    }
+}
+
+pub fn format_block(block: &Block) -> String {
+    let i = block.tile();
+    let suit = format_tile(i).chars().last().unwrap();
+    let n = i % 9 + 1;
+    match *block {
+        Block::Triplet(_) => format!("{0}{0}{0}{1}", n, suit),
+        Block::Sequence(_) => format!("{}{}{}{}", n, n + 1, n + 2, suit),
+        Block::Pair(_) => format!("{0}{0}{1}", n, suit),
+        Block::Adjacent(_) => format!("{}{}{}", n, n + 1, suit),
+        Block::Gapped(_) => format!("{}{}{}", n, n + 2, suit),
+        Block::Single(_) => format!("{}{}", n, suit),
+    }
+}
+
+pub fn format_blocks(blocks: &[Block]) -> String {
+    let texts: Vec<_> = blocks.iter().map(format_block).collect();
+    texts.join(" ")
+}
+
+// calls `f` once for every partition of `hand` into blocks, in ascending order of blocks.
+pub fn for_each_decomposition(hand: &TileSet, f: &mut dyn FnMut(&[Block])) {
+    visit(&mut hand.clone(), 0, 0, false, &mut Vec::new(), f);
+}
+
+pub fn decompositions(hand: &TileSet) -> Vec<Vec<Block>> {
+    let mut dst = Vec::new();
+    for_each_decomposition(hand, &mut |blocks| dst.push(blocks.to_vec()));
+    dst
+}
+
+// the partitions into one pair and triads, i.e. the ways to read a complete hand.
+pub fn complete_decompositions(hand: &TileSet) -> Vec<Vec<Block>> {
+    let mut dst = Vec::new();
+    visit(&mut hand.clone(), 0, 0, true, &mut Vec::new(), &mut |blocks| {
+        if blocks.iter().filter(|b| matches!(b, Block::Pair(_))).count() == 1 {
+            dst.push(blocks.to_vec());
+        }
+    });
+    dst
+}
+
+fn visit(
+    hand: &mut TileSet,
+    i0: usize,
+    kind0: usize,
+    complete: bool,
+    blocks: &mut Vec<Block>,
+    f: &mut dyn FnMut(&[Block]),
+) {
+    let i = match (i0..hand.len()).find(|i| hand.tile(*i) > 0) {
+        Some(i) => i,
+        None => return f(blocks),
+    };
+    // blocks starting at the same tile are taken in the order of their kinds to enumerate each partition once.
+    let kind0 = if i == i0 { kind0 } else { 0 };
+    let is_simple = i < 27;
+    let candidates = [
+        Block::Triplet(i),
+        Block::Sequence(i),
+        Block::Pair(i),
+        Block::Adjacent(i),
+        Block::Gapped(i),
+        Block::Single(i),
+    ];
+    for block in candidates[kind0..].iter() {
+        let is_possible = match *block {
+            Block::Triplet(_) => hand.tile(i) >= 3,
+            Block::Sequence(_) => is_simple && i % 9 < 7 && hand.tile(i + 1) > 0 && hand.tile(i + 2) > 0,
+            Block::Pair(_) => hand.tile(i) >= 2 && !(complete && blocks.iter().any(|b| matches!(b, Block::Pair(_)))),
+            Block::Adjacent(_) => !complete && is_simple && i % 9 < 8 && hand.tile(i + 1) > 0,
+            Block::Gapped(_) => !complete && is_simple && i % 9 < 7 && hand.tile(i + 2) > 0,
+            Block::Single(_) => !complete,
+        };
+        if !is_possible {
+            continue;
+        }
+        let tiles = block.tiles();
+        for (j, n) in tiles.iter().enumerate() {
+            *hand.tile_mut(j) -= n;
+        }
+        blocks.push(*block);
+        visit(hand, i, block.kind(), complete, blocks, f);
+        blocks.pop();
+        for (j, n) in tiles.iter().enumerate() {
+            *hand.tile_mut(j) += n;
+        }
+    }
+}
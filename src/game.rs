@@ -0,0 +1,684 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::score::*;
+use crate::shanten;
+use crate::table::*;
+use crate::tile::*;
+use std::*;
+
+pub const N_PLAYERS: usize = 4;
+pub const INITIAL_SCORE: isize = 25000;
+const N_HAND: usize = 13;
+const N_MAX_KANS: usize = 4;
+// the dead wall holds 4 replacement tiles for kans, then 5 dora indicators and 5 ura dora indicators.
+const N_RINSHAN: usize = 4;
+const N_INDICATORS: usize = 5;
+const RIICHI_DEPOSIT: isize = 1000;
+const NOTEN_PAYMENT: isize = 3000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Discard(usize),
+    // declares riichi with the discard.
+    Riichi(usize),
+    Tsumo,
+    ClosedKan(usize),
+    // adds a tile to a pon.
+    AddedKan(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Call {
+    Pass,
+    Ron,
+    // the smallest tile of the sequence.
+    Chi(usize),
+    Pon,
+    Kan,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Discard {
+    pub tile: usize,
+    pub riichi: bool,
+    // taken by another player's call.
+    pub called: bool,
+}
+
+// the discards forbidden right after calling `tile` into `meld` (kuikae): the called tile, and the other end of a run
+// of four around the meld of a chi on an end, e.g. 5m after a chi of 2m with 34m.
+pub fn kuikae(tile: usize, meld: Meld) -> Vec<usize> {
+    let mut dst = vec![tile];
+    match meld {
+        Meld::Chi(i) if i == tile && i % 9 < 6 => dst.push(i + 3),
+        Meld::Chi(i) if i + 2 == tile && i % 9 > 0 => dst.push(i - 1),
+        _ => (),
+    }
+    dst
+}
+
+// a call of `tile` into `meld` is allowed only if `hand`, before the call, keeps a discard besides the kuikae ones.
+pub fn can_discard_after(hand: &TileSet, tile: usize, meld: Meld) -> bool {
+    let mut consumed = meld.tiles();
+    *consumed.tile_mut(tile) -= 1;
+    let forbidden = kuikae(tile, meld);
+    (0..N_KINDS).any(|i| hand.tile(i) > consumed.tile(i) && !forbidden.contains(&i))
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Player {
+    // concealed; only the player's own strategy sees it through `View::hand`.
+    hand: TileSet,
+    pub melds: Vec<Meld>,
+    pub discards: Vec<Discard>,
+    pub riichi: bool,
+    double_riichi: bool,
+    ippatsu: bool,
+    // passed a ron since the own last discard.
+    furiten: bool,
+}
+
+impl Player {
+    pub fn n_concealed(&self) -> usize {
+        self.hand.count()
+    }
+
+    pub fn is_menzen(&self) -> bool {
+        self.melds.iter().all(|m| !m.is_open())
+    }
+
+    // the discards left in front of the player, i.e. without the called ones.
+    pub fn pond(&self) -> TileSet {
+        let mut dst = TileSet::new();
+        for d in self.discards.iter().filter(|d| !d.called) {
+            *dst.tile_mut(d.tile) += 1;
+        }
+        dst
+    }
+
+    fn is_furiten(&self) -> bool {
+        if self.furiten {
+            return true;
+        }
+        let mut hand = self.hand.clone();
+        let n_tiles = hand.count() + 1;
+        self.discards.iter().any(|d| {
+            if hand.add(d.tile).is_err() {
+                return false;
+            }
+            let is_winning =
+                count_head_and_triad(&mut hand, false) >= n_tiles || is_chiitoitsu(&hand) || is_kokushi(&hand);
+            hand.remove(d.tile).unwrap();
+            is_winning
+        })
+    }
+}
+
+// what a player knows of the round.
+pub struct View<'a> {
+    pub seat: usize,
+    pub hand: &'a TileSet,
+    pub players: &'a [Player],
+    pub dora_indicators: &'a [usize],
+    // # of tiles left to draw.
+    pub n_live: usize,
+    pub seat_wind: usize,
+    pub round_wind: usize,
+    pub scores: [isize; N_PLAYERS],
+}
+
+impl View<'_> {
+    pub fn player(&self) -> &Player {
+        &self.players[self.seat]
+    }
+
+    // the `i`-th opponent, counted from shimocha.
+    pub fn opponent(&self, i: usize) -> &Player {
+        &self.players[(self.seat + 1 + i) % N_PLAYERS]
+    }
+
+    pub fn table(&self) -> TableState {
+        let mut table = TableState::new();
+        table.own_discards = self.player().pond();
+        for (i, o) in table.opponents.iter_mut().enumerate() {
+            let p = self.opponent(i);
+            o.discards = p.pond();
+            o.riichi = p.riichi;
+        }
+        for p in self.players.iter() {
+            for m in p.melds.iter() {
+                for (i, n) in m.tiles().iter().enumerate() {
+                    *table.melds.tile_mut(i) += n;
+                }
+            }
+        }
+        for i in self.dora_indicators.iter() {
+            *table.dora_indicators.tile_mut(*i) += 1;
+        }
+        table.opponent_tiles = (0..N_PLAYERS - 1).map(|i| self.opponent(i).n_concealed()).sum();
+        table.seat_wind = self.seat_wind;
+        table.round_wind = self.round_wind;
+        table
+    }
+}
+
+pub trait Strategy {
+    // chooses one of the legal `actions` for a hand of 3n + 2 tiles, after a draw or a call.
+    fn act(&mut self, view: &View, actions: &[Action]) -> Action;
+    // chooses one of the legal `calls` on `tile` discarded by `from`.  `Call::Pass` is always legal.
+    fn call(&mut self, view: &View, tile: usize, from: usize, calls: &[Call]) -> Call;
+}
+
+#[derive(Clone, Debug)]
+pub struct RoundConfig {
+    pub round_wind: usize,
+    pub dealer: usize,
+    pub honba: usize,
+    // # of riichi deposits left on the table.
+    pub deposits: usize,
+    pub scores: [isize; N_PLAYERS],
+}
+
+impl Default for RoundConfig {
+    fn default() -> Self {
+        RoundConfig {
+            round_wind: 0,
+            dealer: 0,
+            honba: 0,
+            deposits: 0,
+            scores: [INITIAL_SCORE; N_PLAYERS],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Outcome {
+    Tsumo { winner: usize, score: Score },
+    Ron { winner: usize, from: usize, score: Score },
+    Ryuukyoku { tenpai: [bool; N_PLAYERS] },
+}
+
+#[derive(Clone, Debug)]
+pub struct RoundResult {
+    pub outcome: Outcome,
+    pub deltas: [isize; N_PLAYERS],
+    // # of riichi deposits left on the table for the next round.
+    pub deposits: usize,
+    pub players: Vec<Player>,
+}
+
+struct Round<'a> {
+    config: &'a RoundConfig,
+    players: Vec<Player>,
+    wall: Vec<usize>,
+    dead_wall: Vec<usize>,
+    n_kans: usize,
+    deltas: [isize; N_PLAYERS],
+    deposits: usize,
+    // no call has been made and nobody has discarded twice.
+    first_turn: bool,
+    // the discards forbidden to the caller right after a chi or pon.
+    kuikae: Vec<usize>,
+}
+
+// plays a round with red fives left out.  a strategy choosing an illegal action discards the drawn tile instead,
+// and one choosing an illegal call passes.  only the first player in turn order wins on a discard.
+pub fn play_round<R: rand::Rng>(
+    config: &RoundConfig,
+    strategies: &mut [&mut dyn Strategy],
+    rng: &mut R,
+) -> RoundResult {
+    assert_eq!(strategies.len(), N_PLAYERS);
+    let mut tiles = Vec::new();
+    for i in 0..N_KINDS {
+        for _ in 0..N_COPIES {
+            tiles.push(i);
+        }
+    }
+    rand::seq::SliceRandom::shuffle(&mut tiles[..], rng);
+
+    let dead_wall = tiles.split_off(tiles.len() - N_RINSHAN - 2 * N_INDICATORS);
+    let mut players = vec![Player::default(); N_PLAYERS];
+    for p in players.iter_mut() {
+        for t in tiles.drain(tiles.len() - N_HAND..) {
+            *p.hand.tile_mut(t) += 1;
+        }
+    }
+    let mut round = Round {
+        config,
+        players,
+        wall: tiles,
+        dead_wall,
+        n_kans: 0,
+        deltas: [0; N_PLAYERS],
+        deposits: config.deposits,
+        first_turn: true,
+        kuikae: Vec::new(),
+    };
+    let outcome = round.play(strategies);
+    RoundResult {
+        outcome,
+        deltas: round.deltas,
+        deposits: round.deposits,
+        players: round.players,
+    }
+}
+
+impl Round<'_> {
+    fn play(&mut self, strategies: &mut [&mut dyn Strategy]) -> Outcome {
+        let mut seat = self.config.dealer;
+        let mut drawn = self.draw(seat);
+        let mut rinshan = false;
+        loop {
+            let actions = self.actions(seat, drawn, rinshan);
+            let action = strategies[seat].act(&self.view(seat), &actions);
+            let action = match action {
+                a if actions.contains(&a) => a,
+                _ => *actions.iter().rev().find(|a| matches!(a, Action::Discard(_))).unwrap(),
+            };
+
+            let tile = match action {
+                Action::Tsumo => {
+                    let score = self
+                        .score(seat, drawn.unwrap(), WinType::Tsumo, rinshan, false)
+                        .unwrap();
+                    self.settle_tsumo(seat, &score);
+                    return Outcome::Tsumo { winner: seat, score };
+                }
+                Action::ClosedKan(t) => {
+                    *self.players[seat].hand.tile_mut(t) -= 4;
+                    self.players[seat].melds.push(Meld::ClosedKan(t));
+                    drawn = Some(self.kan(seat));
+                    rinshan = true;
+                    continue;
+                }
+                Action::AddedKan(t) => {
+                    if let Some(outcome) = self.ron(seat, t, true, strategies) {
+                        return outcome;
+                    }
+                    let p = &mut self.players[seat];
+                    *p.hand.tile_mut(t) -= 1;
+                    let m = p.melds.iter_mut().find(|m| **m == Meld::Pon(t)).unwrap();
+                    *m = Meld::Kan(t);
+                    drawn = Some(self.kan(seat));
+                    rinshan = true;
+                    continue;
+                }
+                Action::Discard(t) | Action::Riichi(t) => t,
+            };
+
+            let riichi = matches!(action, Action::Riichi(_));
+            self.kuikae.clear();
+            let p = &mut self.players[seat];
+            *p.hand.tile_mut(tile) -= 1;
+            p.discards.push(Discard {
+                tile,
+                riichi,
+                called: false,
+            });
+            if riichi {
+                p.riichi = true;
+                p.double_riichi = self.first_turn;
+                p.ippatsu = true;
+            } else {
+                p.ippatsu = false;
+                if !p.riichi {
+                    p.furiten = false;
+                }
+            }
+            if seat == (self.config.dealer + N_PLAYERS - 1) % N_PLAYERS {
+                self.first_turn = false;
+            }
+
+            if let Some(outcome) = self.ron(seat, tile, false, strategies) {
+                return outcome;
+            }
+            if riichi {
+                self.deltas[seat] -= RIICHI_DEPOSIT;
+                self.deposits += 1;
+            }
+            if self.wall.is_empty() {
+                return self.ryuukyoku();
+            }
+
+            match self.calls(seat, tile, strategies) {
+                Some((caller, call)) => {
+                    self.players[seat].discards.last_mut().unwrap().called = true;
+                    self.first_turn = false;
+                    for p in self.players.iter_mut() {
+                        p.ippatsu = false;
+                    }
+                    let p = &mut self.players[caller];
+                    let meld = match call {
+                        Call::Chi(i) => Meld::Chi(i),
+                        Call::Pon => Meld::Pon(tile),
+                        _ => Meld::Kan(tile),
+                    };
+                    let mut tiles = meld.tiles();
+                    *tiles.tile_mut(tile) -= 1;
+                    for (i, n) in tiles.iter().enumerate() {
+                        *p.hand.tile_mut(i) -= n;
+                    }
+                    p.melds.push(meld);
+                    if call != Call::Kan {
+                        self.kuikae = kuikae(tile, meld);
+                    }
+                    seat = caller;
+                    rinshan = call == Call::Kan;
+                    drawn = if rinshan { Some(self.kan(seat)) } else { None };
+                }
+                None => {
+                    seat = (seat + 1) % N_PLAYERS;
+                    drawn = self.draw(seat);
+                    rinshan = false;
+                }
+            }
+        }
+    }
+
+    fn view(&self, seat: usize) -> View<'_> {
+        let mut scores = self.config.scores;
+        for (s, d) in scores.iter_mut().zip(self.deltas.iter()) {
+            *s += d;
+        }
+        View {
+            seat,
+            hand: &self.players[seat].hand,
+            players: &self.players,
+            dora_indicators: self.dora_indicators(),
+            n_live: self.wall.len(),
+            seat_wind: self.seat_wind(seat),
+            round_wind: self.config.round_wind,
+            scores,
+        }
+    }
+
+    fn seat_wind(&self, seat: usize) -> usize {
+        (seat + N_PLAYERS - self.config.dealer) % N_PLAYERS
+    }
+
+    fn dora_indicators(&self) -> &[usize] {
+        &self.dead_wall[N_RINSHAN..N_RINSHAN + 1 + self.n_kans]
+    }
+
+    fn draw(&mut self, seat: usize) -> Option<usize> {
+        let tile = self.wall.pop()?;
+        *self.players[seat].hand.tile_mut(tile) += 1;
+        Some(tile)
+    }
+
+    // draws a replacement tile from the dead wall, which takes the last tile of the live wall instead.
+    fn kan(&mut self, seat: usize) -> usize {
+        let tile = self.dead_wall[self.n_kans];
+        self.n_kans += 1;
+        self.wall.remove(0);
+        self.first_turn = false;
+        for p in self.players.iter_mut() {
+            p.ippatsu = false;
+        }
+        *self.players[seat].hand.tile_mut(tile) += 1;
+        tile
+    }
+
+    fn actions(&self, seat: usize, drawn: Option<usize>, rinshan: bool) -> Vec<Action> {
+        let p = &self.players[seat];
+        let mut dst = Vec::new();
+        if let Some(t) = drawn {
+            if self.score(seat, t, WinType::Tsumo, rinshan, false).is_some() {
+                dst.push(Action::Tsumo);
+            }
+        }
+        if p.riichi {
+            dst.push(Action::Discard(drawn.unwrap()));
+            return dst;
+        }
+
+        let can_kan = drawn.is_some() && self.n_kans < N_MAX_KANS && !self.wall.is_empty();
+        let can_riichi = p.is_menzen()
+            && drawn.is_some()
+            && self.wall.len() >= N_PLAYERS
+            && self.config.scores[seat] + self.deltas[seat] >= RIICHI_DEPOSIT;
+        let mut hand = p.hand.clone();
+        for i in 0..N_KINDS {
+            if hand.tile(i) == 0 || self.kuikae.contains(&i) {
+                continue;
+            }
+            dst.push(Action::Discard(i));
+            if can_riichi {
+                *hand.tile_mut(i) -= 1;
+                if shanten::shanten(&hand) == 0 {
+                    dst.push(Action::Riichi(i));
+                }
+                *hand.tile_mut(i) += 1;
+            }
+            if can_kan && hand.tile(i) == 4 {
+                dst.push(Action::ClosedKan(i));
+            }
+            if can_kan && p.melds.contains(&Meld::Pon(i)) {
+                dst.push(Action::AddedKan(i));
+            }
+        }
+        // the drawn tile comes last, to be discarded in place of an illegal action.
+        if let Some(t) = drawn {
+            let i = dst.iter().position(|a| *a == Action::Discard(t)).unwrap();
+            let a = dst.remove(i);
+            dst.push(a);
+        }
+        dst
+    }
+
+    fn context(&self, seat: usize, win_type: WinType) -> WinContext {
+        let p = &self.players[seat];
+        let mut ctx = WinContext::new(win_type);
+        ctx.seat_wind = self.seat_wind(seat);
+        ctx.round_wind = self.config.round_wind;
+        ctx.dora_indicators = self.dora_indicators().to_vec();
+        if p.riichi {
+            let i = N_RINSHAN + N_INDICATORS;
+            ctx.ura_indicators = self.dead_wall[i..i + 1 + self.n_kans].to_vec();
+        }
+        ctx.riichi = p.riichi && !p.double_riichi;
+        ctx.double_riichi = p.double_riichi;
+        ctx.ippatsu = p.ippatsu;
+        ctx.last_tile = self.wall.is_empty();
+        ctx.first_turn = self.first_turn && p.discards.is_empty();
+        ctx.honba = self.config.honba;
+        ctx
+    }
+
+    // the score of the hand of `seat` with `tile`, which is already in the hand for a tsumo.
+    fn score(&self, seat: usize, tile: usize, win_type: WinType, rinshan: bool, chankan: bool) -> Option<Score> {
+        let p = &self.players[seat];
+        let mut hand = p.hand.clone();
+        if win_type == WinType::Ron {
+            hand.add(tile).ok()?;
+        }
+        let mut ctx = self.context(seat, win_type);
+        ctx.rinshan = rinshan;
+        ctx.chankan = chankan;
+        score_hand(&hand, &p.melds, tile, &ctx)
+    }
+
+    // offers a ron on `tile` to the other players in turn order.
+    fn ron(
+        &mut self,
+        from: usize,
+        tile: usize,
+        chankan: bool,
+        strategies: &mut [&mut dyn Strategy],
+    ) -> Option<Outcome> {
+        for k in 1..N_PLAYERS {
+            let seat = (from + k) % N_PLAYERS;
+            let score = match self.score(seat, tile, WinType::Ron, false, chankan) {
+                Some(s) if !self.players[seat].is_furiten() => s,
+                _ => continue,
+            };
+            let calls = [Call::Pass, Call::Ron];
+            if strategies[seat].call(&self.view(seat), tile, from, &calls) == Call::Ron {
+                self.settle_ron(seat, from, &score);
+                return Some(Outcome::Ron {
+                    winner: seat,
+                    from,
+                    score,
+                });
+            }
+            self.players[seat].furiten = true;
+        }
+        None
+    }
+
+    // offers pon, kan and chi on `tile`.  a pon or kan takes priority over a chi.
+    fn calls(&mut self, from: usize, tile: usize, strategies: &mut [&mut dyn Strategy]) -> Option<(usize, Call)> {
+        let mut chosen = None;
+        for k in 1..N_PLAYERS {
+            let seat = (from + k) % N_PLAYERS;
+            let p = &self.players[seat];
+            if p.riichi {
+                continue;
+            }
+            let mut calls = vec![Call::Pass];
+            if p.hand.tile(tile) >= 2 && can_discard_after(&p.hand, tile, Meld::Pon(tile)) {
+                calls.push(Call::Pon);
+            }
+            if p.hand.tile(tile) >= 3 && self.n_kans < N_MAX_KANS {
+                calls.push(Call::Kan);
+            }
+            if k == 1 && tile < 27 {
+                for i in tile.saturating_sub(2)..=tile {
+                    let is_sequence = i / 9 == tile / 9 && i % 9 < 7;
+                    if is_sequence
+                        && (i..i + 3).all(|j| j == tile || p.hand.tile(j) > 0)
+                        && can_discard_after(&p.hand, tile, Meld::Chi(i))
+                    {
+                        calls.push(Call::Chi(i));
+                    }
+                }
+            }
+            if calls.len() == 1 {
+                continue;
+            }
+            let call = strategies[seat].call(&self.view(seat), tile, from, &calls);
+            if call == Call::Pass || !calls.contains(&call) {
+                continue;
+            }
+            if !matches!(call, Call::Chi(_)) || chosen.is_none() {
+                chosen = Some((seat, call));
+            }
+        }
+        chosen
+    }
+
+    fn settle_tsumo(&mut self, winner: usize, score: &Score) {
+        for seat in 0..N_PLAYERS {
+            if seat == winner {
+                continue;
+            }
+            let paid = match score.payment {
+                Payment::TsumoAll(p) => p,
+                Payment::Tsumo(_, p) if seat == self.config.dealer => p,
+                Payment::Tsumo(p, _) => p,
+                Payment::Ron(p) => p,
+            } as isize;
+            self.deltas[seat] -= paid;
+            self.deltas[winner] += paid;
+        }
+        self.take_deposits(winner);
+    }
+
+    fn settle_ron(&mut self, winner: usize, from: usize, score: &Score) {
+        self.deltas[from] -= score.points as isize;
+        self.deltas[winner] += score.points as isize;
+        self.take_deposits(winner);
+    }
+
+    fn take_deposits(&mut self, winner: usize) {
+        self.deltas[winner] += self.deposits as isize * RIICHI_DEPOSIT;
+        self.deposits = 0;
+    }
+
+    // the tenpai players are paid 3000 points in total by the others.
+    fn ryuukyoku(&mut self) -> Outcome {
+        let mut tenpai = [false; N_PLAYERS];
+        for (t, p) in tenpai.iter_mut().zip(self.players.iter()) {
+            *t = shanten::shanten(&p.hand) == 0;
+        }
+        let n_tenpai = tenpai.iter().filter(|t| **t).count() as isize;
+        if 0 < n_tenpai && n_tenpai < N_PLAYERS as isize {
+            for (d, t) in self.deltas.iter_mut().zip(tenpai.iter()) {
+                *d += match t {
+                    true => NOTEN_PAYMENT / n_tenpai,
+                    false => -NOTEN_PAYMENT / (N_PLAYERS as isize - n_tenpai),
+                };
+            }
+        }
+        Outcome::Ryuukyoku { tenpai }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameConfig {
+    // 1 for an east-only game, 2 for east and south.
+    pub n_winds: usize,
+    pub scores: isize,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        GameConfig {
+            n_winds: 2,
+            scores: INITIAL_SCORE,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    pub scores: [isize; N_PLAYERS],
+    // 0 for the top.  ties go to the seat nearer to the first dealer.
+    pub ranks: [usize; N_PLAYERS],
+    pub rounds: Vec<RoundResult>,
+}
+
+// seat 0 deals first.  the dealer keeps dealing after a win or tenpai at an exhaustive draw, and the game ends early
+// when a score goes below zero.  the deposits left at the end go to the top.
+pub fn play_game<R: rand::Rng>(config: &GameConfig, strategies: &mut [&mut dyn Strategy], rng: &mut R) -> GameResult {
+    let mut round = RoundConfig {
+        scores: [config.scores; N_PLAYERS],
+        ..Default::default()
+    };
+    let mut rounds = Vec::new();
+    while round.round_wind < config.n_winds {
+        let result = play_round(&round, strategies, rng);
+        for (s, d) in round.scores.iter_mut().zip(result.deltas.iter()) {
+            *s += d;
+        }
+        round.deposits = result.deposits;
+        let (renchan, ryuukyoku) = match result.outcome {
+            Outcome::Tsumo { winner, .. } | Outcome::Ron { winner, .. } => (winner == round.dealer, false),
+            Outcome::Ryuukyoku { tenpai } => (tenpai[round.dealer], true),
+        };
+        round.honba = if renchan || ryuukyoku { round.honba + 1 } else { 0 };
+        if !renchan {
+            round.dealer = (round.dealer + 1) % N_PLAYERS;
+            if round.dealer == 0 {
+                round.round_wind += 1;
+            }
+        }
+        rounds.push(result);
+        if round.scores.iter().any(|s| *s < 0) {
+            break;
+        }
+    }
+
+    let mut order: Vec<_> = (0..N_PLAYERS).collect();
+    order.sort_by_key(|i| cmp::Reverse(round.scores[*i]));
+    round.scores[order[0]] += round.deposits as isize * RIICHI_DEPOSIT;
+    let mut ranks = [0; N_PLAYERS];
+    for (r, i) in order.iter().enumerate() {
+        ranks[*i] = r;
+    }
+    GameResult {
+        scores: round.scores,
+        ranks,
+        rounds,
+    }
+}
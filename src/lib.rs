@@ -1,6 +1,24 @@
 // (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+pub mod arena;
 pub mod bench;
+pub mod capi;
+pub mod danger;
+pub mod decompose;
+pub mod game;
+pub mod mjai;
+pub mod notation;
 pub mod playout;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod repl;
+pub mod replay;
+pub mod review;
+pub mod score;
 pub mod search;
+pub mod server;
+pub mod shanten;
+pub mod strategy;
+pub mod table;
 pub mod tile;
 pub mod uct;
+pub mod wait;
@@ -1,22 +1,448 @@
 // (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use rand::SeedableRng;
+use rayon::prelude::*;
 use std::*;
 use teff::bench;
+use teff::mjai;
+use teff::repl;
+use teff::replay;
+use teff::review;
+use teff::server;
+use teff::strategy;
+use teff::table::*;
 use teff::tile::*;
 
+const USAGE: &str = "\
+Usage: teff [<command>] [<options>] [<hands>...]
+
+Commands:
+  analyze   analyzes each hand by every engine (the default with hands)
+  bench     plays random hands to compare the engines (the default without hands)
+  shanten   shanten numbers and ukeire of each hand, or of each discard
+  waits     waits, live tiles and points of each tenpai hand
+  review    grades the discards of a player in a Tenhou log
+  arena     plays games between the engines: arena [<n_games>]
+  mjai      plays on an mjai server, or on stdio: mjai [<host:port>]
+  batch     analyzes the lines of a file or stdin in parallel: batch [<file>]
+  serve     serves the analyses over HTTP: serve [<host:port>] (127.0.0.1:8080)
+  repl      explores a hand by the commands from stdin: repl [<hand>]
+
+Engine options:
+  --engine <name>          only search, playout or uct (mjai: also ukeire)
+  --depth <n>              max depth of the search (3, review: 2)
+  --playout-depth <n>      depth of the playouts (1)
+  --samples <n>            # of samples of the playouts and UCT (2097152)
+  --threads <n>            # of worker threads
+  --seed <n>               seed of the random deals and samplers
+
+Table options, applied to the hands after them:
+  -o, --own-discards <tiles>
+  -d, --discards <tiles>   discards whose owner is not given
+  -1, --shimocha <tiles>   discards of each opponent
+  -2, --toimen <tiles>
+  -3, --kamicha <tiles>
+  --riichi <1-3...>        the opponents in riichi
+  -m, --melds <tiles>
+  -i, --dora <tiles>       dora indicators
+  --seat <E|S|W|N>         seat wind (review: the player, 0-3)
+  --round <E|S|W|N>        round wind
+  --opponent-tiles <n>     # of concealed tiles of the opponents
+  --dead-wall <n>          # of tiles in the dead wall
+
+Output options:
+  --json                   one json object for each hand, or for the review
+  --csv                    a csv row for each estimate of each hand
+
+Review options:
+  --threshold <score>      flags the discards worse than the best by more than it (0.1)
+
+mjai options:
+  --name <name>
+
+serve options:
+  --workers <n>            # of the threads serving the connections (4)
+  --time-limit <seconds>   the time to wait for each request (30)
+";
+
+const COMMANDS: [&str; 10] = [
+    "analyze", "bench", "shanten", "waits", "review", "arena", "mjai", "repl", "batch", "serve",
+];
+
+struct Options {
+    command: &'static str,
+    params: bench::Params,
+    // the depth if given, as review has its own default.
+    depth: Option<usize>,
+    seed: Option<u64>,
+    rng: rand::rngs::StdRng,
+    table: TableState,
+    player: usize,
+    threshold: f64,
+    format: bench::Format,
+    // whether the csv header is printed.
+    has_header: bool,
+    name: String,
+    server: server::Config,
+}
+
+fn parse_number<T: str::FromStr>(arg: &str, text: Option<String>) -> Result<T, String> {
+    text.and_then(|t| t.parse().ok())
+        .ok_or_else(|| format!("Missing number after {}", arg))
+}
+
+// applies an option taking a value, or returns `Ok(false)` if `arg` is not an option.
+fn parse_option(arg: &str, args: &mut impl Iterator<Item = String>, o: &mut Options) -> Result<bool, String> {
+    match arg {
+        "--engine" => {
+            let engines: &[&str] = match o.command {
+                "review" => &["search", "playout"],
+                "mjai" => &["ukeire", "search", "playout", "uct"],
+                _ => &["search", "playout", "uct"],
+            };
+            let engine = args
+                .next()
+                .filter(|e| engines.contains(&e.as_str()))
+                .ok_or_else(|| format!("Missing engine ({}) after {}", engines.join(", "), arg))?;
+            o.params.engine = Some(engine);
+        }
+        "--depth" => {
+            o.params.depth = parse_number(arg, args.next())?;
+            o.depth = Some(o.params.depth);
+        }
+        "--playout-depth" => o.params.playout_depth = parse_number(arg, args.next())?,
+        "--samples" => o.params.n_samples = parse_number(arg, args.next())?,
+        "--threads" => {
+            let n = parse_number(arg, args.next())?;
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(n)
+                .build_global()
+                .map_err(|e| e.to_string())?;
+        }
+        "--seed" => {
+            let seed = parse_number(arg, args.next())?;
+            o.seed = Some(seed);
+            o.rng = rand::rngs::StdRng::seed_from_u64(seed);
+        }
+        "--threshold" => o.threshold = parse_number(arg, args.next())?,
+        "--json" => o.format = bench::Format::Json,
+        "--csv" => o.format = bench::Format::Csv,
+        "--workers" => o.server.n_workers = parse_number(arg, args.next())?,
+        "--time-limit" => {
+            o.server.time_limit = time::Duration::try_from_secs_f64(parse_number(arg, args.next())?)
+                .map_err(|_| format!("Invalid seconds after {}", arg))?;
+        }
+        "--name" => o.name = args.next().ok_or_else(|| format!("Missing name after {}", arg))?,
+        "--seat" if o.command == "review" => {
+            o.player = parse_number(arg, args.next())?;
+            if o.player >= 4 {
+                return Err(format!("Invalid player (0-3) after {}", arg));
+            }
+        }
+        _ => {
+            let parse = |t: &str| parse_tiles(t).ok_or_else(String::new);
+            return parse_table_option(arg, args, &mut o.table, parse);
+        }
+    }
+    Ok(true)
+}
+
+// applies an option of the table, parsing the tiles by `parse`, or returns `Ok(false)` if `arg` is not one.
+fn parse_table_option(
+    arg: &str,
+    args: &mut impl Iterator<Item = String>,
+    table: &mut TableState,
+    parse: impl Fn(&str) -> Result<TileSet, String>,
+) -> Result<bool, String> {
+    match arg {
+        "--opponent-tiles" => table.opponent_tiles = parse_number(arg, args.next())?,
+        "--dead-wall" => table.dead_wall = parse_number(arg, args.next())?,
+        "--seat" | "--round" => {
+            let wind = args
+                .next()
+                .and_then(|t| ["E", "S", "W", "N"].iter().position(|e| *e == t))
+                .ok_or_else(|| format!("Missing wind (E, S, W or N) after {}", arg))?;
+            match arg {
+                "--seat" => table.seat_wind = wind,
+                _ => table.round_wind = wind,
+            }
+        }
+        "--riichi" => {
+            // the opponents in riichi, as digits of 1 (shimocha), 2 (toimen) and 3 (kamicha).
+            let t = args
+                .next()
+                .filter(|t| t.chars().all(|c| ('1'..='3').contains(&c)))
+                .ok_or_else(|| format!("Missing opponents (1, 2 or 3) after {}", arg))?;
+            for (i, o) in table.opponents.iter_mut().enumerate() {
+                o.riichi = t.contains(char::from(b'1' + i as u8));
+            }
+        }
+        _ => {
+            let [shimocha, toimen, kamicha] = &mut table.opponents;
+            let field = match arg {
+                "-o" | "--own-discards" => &mut table.own_discards,
+                "-d" | "--discards" => &mut table.discards,
+                "-1" | "--shimocha" => &mut shimocha.discards,
+                "-2" | "--toimen" => &mut toimen.discards,
+                "-3" | "--kamicha" => &mut kamicha.discards,
+                "-m" | "--melds" => &mut table.melds,
+                "-i" | "--dora" => &mut table.dora_indicators,
+                _ => return Ok(false),
+            };
+            let text = args.next().ok_or_else(|| format!("Missing tiles after {}", arg))?;
+            *field = parse(&text)?;
+        }
+    }
+    Ok(true)
+}
+
+fn parse_tiles(text: &str) -> Option<TileSet> {
+    match parse_tile_set(text) {
+        Ok(t) => Some(t),
+        Err(e) => {
+            println!("Syntax error: {}", text);
+            println!("              {}^ {}", " ".repeat(e.pos), e.kind);
+            println!();
+            None
+        }
+    }
+}
+
+// the syntax error as a message, for the outputs other than the text.
+fn parse_tiles_or_error(text: &str) -> Result<TileSet, String> {
+    parse_tile_set(text).map_err(|e| format!("Syntax error at {}: {}", e.pos, e.kind))
+}
+
+fn review(path: &str, o: &Options) {
+    let engine = match o.params.engine.as_deref() {
+        Some("playout") => review::Engine::Playout {
+            depth: o.params.playout_depth,
+            n_samples: o.params.n_samples,
+            seed: o.seed.unwrap_or_else(rand::random),
+        },
+        _ => review::Engine::Search {
+            depth: o.depth.unwrap_or(2),
+        },
+    };
+    let grades = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|t| replay::parse(&t).map_err(|e| e.to_string()))
+        .and_then(|r| review::review(&r, o.player, &engine, o.threshold).map_err(|e| e.to_string()));
+    match grades {
+        Ok(g) if o.format == bench::Format::Json => println!("{}", review::to_json(&g, o.player, &engine, o.threshold)),
+        Ok(g) => print!("{}", review::format_text(&g)),
+        Err(e) => println!("{}: {}", path, e),
+    }
+}
+
+fn analyze(text: &str, o: &mut Options) {
+    // the syntax errors are pointed at only in the text.
+    let result = match o.format {
+        bench::Format::Text => match parse_tiles(text) {
+            Some(h) => Ok(h),
+            None => return,
+        },
+        _ => parse_tiles_or_error(text),
+    };
+    let result = result.and_then(|mut h| bench::analyze_hand(&mut h, &o.table, &o.params, o.format, &mut o.rng));
+    match (result, o.format) {
+        (Ok(a), bench::Format::Json) => println!("{}", bench::to_json(&a)),
+        (Ok(a), bench::Format::Csv) => {
+            if !mem::replace(&mut o.has_header, true) {
+                print!("{}", bench::CSV_HEADER);
+            }
+            print!("{}", bench::to_csv(&a));
+        }
+        (Err(e), bench::Format::Json) => println!("{}", serde_json::json!({ "hand": text, "error": e })),
+        (Err(e), bench::Format::Csv) => eprintln!("{}: {}", text, e),
+        _ => (),
+    }
+}
+
+// a hand and the options of its table, e.g. "123m456p789s11223z -d 1m9p --riichi 2".
+fn parse_line(line: &str, table: &TableState) -> Result<(TileSet, TableState), String> {
+    let mut table = table.clone();
+    let mut hand = None;
+    let mut args = line.split_whitespace().map(str::to_string);
+    while let Some(arg) = args.next() {
+        if parse_table_option(&arg, &mut args, &mut table, parse_tiles_or_error)? {
+            continue;
+        }
+        if hand.replace(parse_tiles_or_error(&arg)?).is_some() {
+            return Err(format!("More than one hand: {}", arg));
+        }
+    }
+    Ok((hand.ok_or("Missing hand")?, table))
+}
+
+// analyzes the line of each position on the pool, printing the results as they end, with the # of the line.
+fn batch(path: Option<&String>, o: &mut Options) {
+    let input: Box<dyn io::BufRead + Send> = match path {
+        Some(p) => match fs::File::open(p) {
+            Ok(f) => Box::new(io::BufReader::new(f)),
+            Err(e) => {
+                println!("{}: {}", p, e);
+                return;
+            }
+        },
+        None => Box::new(io::BufReader::new(io::stdin())),
+    };
+    let format = match o.format {
+        bench::Format::Csv => {
+            print!("{}", bench::CSV_HEADER);
+            bench::Format::Csv
+        }
+        _ => bench::Format::Json,
+    };
+    // each line has its own seed, which seeds every sampler of its analysis, so that the results do not depend on the
+    // order.
+    let seed = o.seed.unwrap_or_else(|| rand::Rng::gen(&mut o.rng));
+    let (table, params) = (&o.table, &o.params);
+    let n_done = sync::atomic::AtomicUsize::new(0);
+    io::BufRead::lines(input)
+        .enumerate()
+        .par_bridge()
+        .for_each(|(i, line)| {
+            let line = match line {
+                Ok(l) => l,
+                Err(e) => {
+                    eprintln!("{}: {}", i + 1, e);
+                    return;
+                }
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return;
+            }
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+            let result = parse_line(line, table)
+                .and_then(|(mut hand, table)| bench::analyze_hand(&mut hand, &table, params, format, &mut rng));
+            // a single print, not to mix the lines of the threads.
+            match (result, format) {
+                (Ok(a), bench::Format::Csv) => print!("{}", bench::to_csv(&a)),
+                (Ok(a), _) => {
+                    let mut json = bench::to_json(&a);
+                    json["line"] = serde_json::json!(i + 1);
+                    println!("{}", json);
+                }
+                (Err(e), bench::Format::Csv) => eprintln!("{}: {}", i + 1, e),
+                (Err(e), _) => println!("{}", serde_json::json!({ "line": i + 1, "input": line, "error": e })),
+            }
+            let n = n_done.fetch_add(1, sync::atomic::Ordering::Relaxed) + 1;
+            eprint!("\r{} positions done", n);
+        });
+    eprintln!();
+}
+
+fn mjai(addr: Option<&String>, o: &Options) {
+    let strategy = strategy::by_name(o.params.engine.as_deref().unwrap_or("uct")).unwrap();
+    let mut bot = mjai::Bot::new(strategy, &o.name);
+    let result = match addr {
+        Some(addr) => mjai::connect(&mut bot, addr),
+        None => mjai::run(&mut bot, io::stdin().lock(), io::stdout()),
+    };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
 fn main() {
-    if env::args().len() <= 1 {
-        bench::benchmark(&mut rand::thread_rng());
-    } else {
-        for arg in env::args().skip(1) {
-            let mut hand = match parse_tile_set(&arg) {
-                Some(t) => t,
-                None => {
-                    println!("Syntax error: {}", arg);
-                    println!();
-                    continue;
+    let mut args = env::args().skip(1).peekable();
+    // the commands of older versions were flags.
+    let first = args.peek().map(|a| a.trim_start_matches("--").to_string());
+    let command = match COMMANDS.iter().find(|c| Some(**c) == first.as_deref()) {
+        Some(c) => {
+            args.next();
+            *c
+        }
+        None if first.is_none() => "bench",
+        None => "analyze",
+    };
+    if matches!(first.as_deref(), Some("help") | Some("-h")) {
+        print!("{}", USAGE);
+        return;
+    }
+
+    let mut o = Options {
+        command,
+        params: bench::Params::default(),
+        depth: None,
+        seed: None,
+        rng: rand::rngs::StdRng::from_entropy(),
+        table: TableState::new(),
+        player: 0,
+        threshold: review::DEFAULT_THRESHOLD,
+        format: bench::Format::Text,
+        has_header: false,
+        name: "teff".to_string(),
+        server: server::Config::default(),
+    };
+    let mut positionals = Vec::new();
+    let mut n_hands = 0;
+    while let Some(arg) = args.next() {
+        match parse_option(&arg, &mut args, &mut o) {
+            Ok(true) => continue,
+            Ok(false) => (),
+            Err(e) => {
+                if !e.is_empty() {
+                    println!("{}", e);
                 }
+                return;
+            }
+        }
+        match command {
+            "analyze" => {
+                analyze(&arg, &mut o);
+                n_hands += 1;
+            }
+            "shanten" => {
+                if let Some(hand) = parse_tiles(&arg) {
+                    bench::analyze_shanten(&hand, &o.table);
+                }
+            }
+            "waits" => {
+                if let Some(hand) = parse_tiles(&arg) {
+                    bench::analyze_wait_tiles(&hand, &o.table);
+                }
+            }
+            "review" => review(&arg, &o),
+            _ => positionals.push(arg),
+        }
+    }
+
+    match command {
+        "bench" => bench::benchmark(&o.params, &mut o.rng),
+        // options alone without a command, e.g. "--seed 1", are of the benchmark.
+        "analyze" if n_hands == 0 && first.as_deref() != Some("analyze") => bench::benchmark(&o.params, &mut o.rng),
+        "arena" => {
+            let n_games = positionals.first().and_then(|t| t.parse().ok()).unwrap_or(100);
+            // the seed used to be the second argument.
+            let seed = positionals.get(1).and_then(|t| t.parse().ok()).or(o.seed).unwrap_or(0);
+            bench::arena(n_games, seed);
+        }
+        "mjai" => mjai(positionals.first(), &o),
+        "serve" => {
+            // the requests may ask for the engines up to the given settings.
+            o.server.max_depth = o.params.depth;
+            o.server.max_samples = o.params.n_samples;
+            let addr = positionals.first().map_or("127.0.0.1:8080", |a| a.as_str());
+            if let Err(e) = net::TcpListener::bind(addr).and_then(|l| server::serve(l, &o.server)) {
+                eprintln!("{}: {}", addr, e);
+            }
+        }
+        "batch" => {
+            let path = positionals.first().cloned();
+            batch(path.as_ref(), &mut o);
+        }
+        "repl" => {
+            let hand = match positionals.first().map(|t| parse_tiles(t)) {
+                Some(None) => return,
+                Some(h) => h,
+                None => None,
             };
-            bench::analyze_hand(&mut hand);
+            if let Err(e) = repl::run(io::stdin().lock(), hand, &o.table, &o.params, &mut o.rng) {
+                eprintln!("{}", e);
+            }
         }
+        _ => (),
     }
 }
@@ -0,0 +1,444 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::game::*;
+use crate::notation::*;
+use crate::score::*;
+use crate::shanten;
+use crate::tile::*;
+use crate::wait;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::*;
+
+// 136 tiles - the dead wall - 4 hands.
+const N_LIVE: usize = 136 - 14 - 4 * 13;
+const WINDS: [&str; 4] = ["E", "S", "W", "N"];
+
+// a player of the mjai protocol.  the round is tracked from the events and the actions are chosen by `strategy`.
+pub struct Bot {
+    strategy: Box<dyn Strategy>,
+    name: String,
+    state: State,
+}
+
+// the round seen from our seat.  red fives are kept track of only to name the tiles of our own actions.
+struct State {
+    seat: usize,
+    hand: TileSet,
+    // the fives held as red ones.
+    reds: TileSet,
+    drawn: Option<usize>,
+    players: Vec<Player>,
+    dora_indicators: Vec<usize>,
+    n_live: usize,
+    dealer: usize,
+    round_wind: usize,
+    scores: [isize; N_PLAYERS],
+    // the discard of our riichi declaration, sent after the server echoes the declaration.
+    riichi_discard: Option<usize>,
+    // passed a ron since our last discard.
+    furiten: bool,
+    // the discards forbidden right after our call (kuikae).
+    kuikae: Vec<usize>,
+}
+
+fn mjai_tile(text: &str) -> Result<usize, String> {
+    parse_mjai_tile(text).ok_or_else(|| format!("Unknown tile \"{}\"", text))
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            seat: 0,
+            hand: TileSet::new(),
+            reds: TileSet::new(),
+            drawn: None,
+            players: vec![Player::default(); N_PLAYERS],
+            dora_indicators: Vec::new(),
+            n_live: N_LIVE,
+            dealer: 0,
+            round_wind: 0,
+            scores: [INITIAL_SCORE; N_PLAYERS],
+            riichi_discard: None,
+            furiten: false,
+            kuikae: Vec::new(),
+        }
+    }
+
+    fn start(&mut self, event: &Value) -> Result<(), String> {
+        let wind = event["bakaze"].as_str().unwrap_or("E");
+        *self = State {
+            seat: self.seat,
+            dealer: event["oya"].as_u64().unwrap_or(0) as usize,
+            round_wind: WINDS.iter().position(|w| *w == wind).unwrap_or(0),
+            dora_indicators: event["dora_marker"]
+                .as_str()
+                .and_then(parse_mjai_tile)
+                .into_iter()
+                .collect(),
+            scores: self.scores,
+            ..State::new()
+        };
+        let tehai = event["tehais"][self.seat].as_array().cloned().unwrap_or_default();
+        for text in tehai.iter().filter_map(|t| t.as_str()) {
+            self.take(text)?;
+        }
+        Ok(())
+    }
+
+    fn take(&mut self, text: &str) -> Result<(), String> {
+        let t = mjai_tile(text)?;
+        self.hand.add(t).map_err(|e| e.to_string())?;
+        if text.ends_with('r') {
+            *self.reds.tile_mut(t) += 1;
+        }
+        Ok(())
+    }
+
+    // a tile which is not in our hand is an error, as the events do not match the round we track.
+    fn give(&mut self, text: &str) -> Result<(), String> {
+        let t = mjai_tile(text)?;
+        self.hand.remove(t).map_err(|e| e.to_string())?;
+        if text.ends_with('r') && self.reds.tile(t) > 0 {
+            *self.reds.tile_mut(t) -= 1;
+        }
+        Ok(())
+    }
+
+    fn player(&mut self, actor: usize) -> Result<&mut Player, String> {
+        self.players
+            .get_mut(actor)
+            .ok_or_else(|| format!("Unknown player {}", actor))
+    }
+
+    // the mjai names of tiles taken out of our hand together, where a red five is named only if it is the last
+    // copy.
+    fn names(&self, tiles: &[usize]) -> Vec<String> {
+        let mut hand = self.hand.clone();
+        let mut reds = self.reds.clone();
+        let mut dst = Vec::new();
+        for &t in tiles {
+            let is_red = reds.tile(t) > 0 && hand.tile(t) <= reds.tile(t);
+            dst.push(format_mjai_tile(t) + if is_red { "r" } else { "" });
+            *hand.tile_mut(t) -= 1;
+            if is_red {
+                *reds.tile_mut(t) -= 1;
+            }
+        }
+        dst
+    }
+
+    fn seat_wind(&self) -> usize {
+        (self.seat + N_PLAYERS - self.dealer) % N_PLAYERS
+    }
+
+    fn view(&self) -> View<'_> {
+        View {
+            seat: self.seat,
+            hand: &self.hand,
+            players: &self.players,
+            dora_indicators: &self.dora_indicators,
+            n_live: self.n_live,
+            seat_wind: self.seat_wind(),
+            round_wind: self.round_wind,
+            scores: self.scores,
+        }
+    }
+
+    fn can_win(&self, hand: &TileSet, tile: usize, win_type: WinType) -> bool {
+        let mut ctx = WinContext::new(win_type);
+        ctx.seat_wind = self.seat_wind();
+        ctx.round_wind = self.round_wind;
+        ctx.dora_indicators = self.dora_indicators.clone();
+        ctx.n_red = self.reds.count();
+        ctx.riichi = self.players[self.seat].riichi;
+        ctx.last_tile = self.n_live == 0;
+        score_hand(hand, &self.players[self.seat].melds, tile, &ctx).is_some()
+    }
+
+    // the legal actions for our hand of 3n + 2 tiles, with the tsumogiri last.  kans are never declared, and the
+    // kuikae discards are excluded after a call.
+    fn actions(&self) -> Vec<Action> {
+        let p = &self.players[self.seat];
+        let mut dst = Vec::new();
+        if let Some(t) = self.drawn {
+            if self.can_win(&self.hand, t, WinType::Tsumo) {
+                dst.push(Action::Tsumo);
+            }
+            if p.riichi {
+                dst.push(Action::Discard(t));
+                return dst;
+            }
+        }
+        let can_riichi = p.is_menzen()
+            && !p.riichi
+            && self.drawn.is_some()
+            && self.n_live >= N_PLAYERS
+            && self.scores[self.seat] >= 1000;
+        let mut hand = self.hand.clone();
+        for i in (0..N_KINDS).filter(|i| Some(*i) != self.drawn) {
+            if hand.tile(i) == 0 || self.kuikae.contains(&i) {
+                continue;
+            }
+            dst.push(Action::Discard(i));
+            *hand.tile_mut(i) -= 1;
+            if can_riichi && shanten::shanten(&hand) == 0 {
+                dst.push(Action::Riichi(i));
+            }
+            *hand.tile_mut(i) += 1;
+        }
+        if let Some(t) = self.drawn {
+            *hand.tile_mut(t) -= 1;
+            if can_riichi && shanten::shanten(&hand) == 0 {
+                dst.push(Action::Riichi(t));
+            }
+            dst.push(Action::Discard(t));
+        }
+        dst
+    }
+
+    // the legal calls on a discard of the player `from`.
+    fn calls(&self, tile: usize, from: usize) -> Vec<Call> {
+        let p = &self.players[self.seat];
+        let mut dst = vec![Call::Pass];
+        let mut hand = self.hand.clone();
+        if hand.add(tile).is_ok() && self.can_win(&hand, tile, WinType::Ron) {
+            let mut discards = TileSet::new();
+            for d in p.discards.iter() {
+                *discards.tile_mut(d.tile) += 1;
+            }
+            let waits = wait::winning_tiles(&self.hand, &TileSet::new());
+            if !self.furiten && !wait::is_furiten(&waits, &discards) {
+                dst.push(Call::Ron);
+            }
+        }
+        if p.riichi || self.n_live == 0 {
+            return dst;
+        }
+        if self.hand.tile(tile) >= 2 && can_discard_after(&self.hand, tile, Meld::Pon(tile)) {
+            dst.push(Call::Pon);
+        }
+        if from == (self.seat + N_PLAYERS - 1) % N_PLAYERS && tile < 27 {
+            for i in tile.saturating_sub(2)..=tile {
+                let is_run = i / 9 == tile / 9 && i % 9 < 7;
+                let consumed: Vec<_> = (i..i + 3).filter(|j| *j != tile).collect();
+                if is_run
+                    && consumed.iter().all(|j| self.hand.tile(*j) > 0)
+                    && can_discard_after(&self.hand, tile, Meld::Chi(i))
+                {
+                    dst.push(Call::Chi(i));
+                }
+            }
+        }
+        dst
+    }
+
+    fn dahai(&self, tile: usize) -> Value {
+        json!({
+            "type": "dahai",
+            "actor": self.seat,
+            "pai": self.names(&[tile])[0],
+            "tsumogiri": self.drawn == Some(tile),
+        })
+    }
+}
+
+impl Bot {
+    pub fn new(strategy: Box<dyn Strategy>, name: &str) -> Self {
+        Bot {
+            strategy,
+            name: name.to_string(),
+            state: State::new(),
+        }
+    }
+
+    // the response to an event, or `None` when the game has ended.  an event which does not match the round is an
+    // error.
+    pub fn respond(&mut self, event: &Value) -> Result<Option<Value>, String> {
+        let none = json!({ "type": "none" });
+        let s = &mut self.state;
+        let pai = event["pai"].as_str().unwrap_or("");
+        let actor = event["actor"].as_u64().map_or(usize::MAX, |a| a as usize);
+        let consumed: Vec<_> = event["consumed"]
+            .as_array()
+            .map_or(&[][..], |v| &v[..])
+            .iter()
+            .filter_map(|t| t.as_str())
+            .collect();
+        if let Some(scores) = event["scores"].as_array() {
+            for (dst, v) in s.scores.iter_mut().zip(scores.iter()) {
+                *dst = v.as_i64().unwrap_or(0) as isize;
+            }
+        }
+
+        let response = match event["type"].as_str().unwrap_or("") {
+            "hello" => json!({ "type": "join", "name": self.name, "room": "default" }),
+            "start_game" => {
+                s.seat = event["id"].as_u64().unwrap_or(0) as usize;
+                none
+            }
+            "start_kyoku" => {
+                s.start(event)?;
+                none
+            }
+            "tsumo" => {
+                s.n_live = s.n_live.saturating_sub(1);
+                if actor != s.seat {
+                    return Ok(Some(none));
+                }
+                s.take(pai)?;
+                s.drawn = parse_mjai_tile(pai);
+                self.act()
+            }
+            "reach" => {
+                s.player(actor)?.riichi = true;
+                match s.riichi_discard.take() {
+                    Some(t) if actor == s.seat => s.dahai(t),
+                    _ => none,
+                }
+            }
+            "dahai" => {
+                let tile = mjai_tile(pai)?;
+                if actor == s.seat {
+                    s.give(pai)?;
+                    s.drawn = None;
+                    s.furiten &= s.players[actor].riichi;
+                    s.kuikae.clear();
+                }
+                s.player(actor)?.discards.push(Discard {
+                    tile,
+                    riichi: false,
+                    called: false,
+                });
+                if actor == s.seat {
+                    return Ok(Some(none));
+                }
+                self.call(tile, pai, actor)
+            }
+            kind @ ("chi" | "pon" | "daiminkan") => {
+                let tile = mjai_tile(pai)?;
+                let target = event["target"].as_u64().unwrap_or(0) as usize;
+                if let Some(d) = s.players.get_mut(target).and_then(|p| p.discards.last_mut()) {
+                    d.called = true;
+                }
+                let meld = match kind {
+                    "chi" => Meld::Chi(consumed.iter().filter_map(|t| parse_mjai_tile(t)).fold(tile, cmp::min)),
+                    "pon" => Meld::Pon(tile),
+                    _ => Meld::Kan(tile),
+                };
+                s.player(actor)?.melds.push(meld);
+                if actor != s.seat {
+                    return Ok(Some(none));
+                }
+                for t in consumed.iter() {
+                    s.give(t)?;
+                }
+                if kind == "daiminkan" {
+                    return Ok(Some(none));
+                }
+                s.kuikae = kuikae(tile, meld);
+                self.act()
+            }
+            "ankan" => {
+                let tile = mjai_tile(consumed.first().copied().unwrap_or(""))?;
+                s.player(actor)?.melds.push(Meld::ClosedKan(tile));
+                if actor == s.seat {
+                    for t in consumed.iter() {
+                        s.give(t)?;
+                    }
+                }
+                none
+            }
+            "kakan" => {
+                let tile = mjai_tile(pai)?;
+                for m in s.player(actor)?.melds.iter_mut() {
+                    if *m == Meld::Pon(tile) {
+                        *m = Meld::Kan(tile);
+                    }
+                }
+                if actor == s.seat {
+                    s.give(pai)?;
+                }
+                none
+            }
+            "dora" => {
+                s.dora_indicators
+                    .extend(event["dora_marker"].as_str().and_then(parse_mjai_tile));
+                none
+            }
+            "end_game" | "error" => return Ok(None),
+            _ => none,
+        };
+        Ok(Some(response))
+    }
+
+    // our action after a draw or a call.
+    fn act(&mut self) -> Value {
+        let s = &mut self.state;
+        let actions = s.actions();
+        let action = match self.strategy.act(&s.view(), &actions) {
+            a if actions.contains(&a) => a,
+            _ => *actions.last().unwrap(),
+        };
+        match action {
+            Action::Tsumo => {
+                let pai = s.names(&[s.drawn.unwrap()]).pop();
+                json!({ "type": "hora", "actor": s.seat, "target": s.seat, "pai": pai })
+            }
+            Action::Riichi(t) => {
+                s.riichi_discard = Some(t);
+                json!({ "type": "reach", "actor": s.seat })
+            }
+            Action::Discard(t) | Action::ClosedKan(t) | Action::AddedKan(t) => s.dahai(t),
+        }
+    }
+
+    // our call on the discard `pai` of the player `from`, which is echoed to keep its red five.
+    fn call(&mut self, tile: usize, pai: &str, from: usize) -> Value {
+        let s = &mut self.state;
+        let calls = s.calls(tile, from);
+        if calls.len() <= 1 {
+            return json!({ "type": "none" });
+        }
+        let call = self.strategy.call(&s.view(), tile, from, &calls);
+        if calls.contains(&Call::Ron) && call != Call::Ron {
+            s.furiten = true;
+        }
+
+        let (kind, consumed) = match call {
+            c if !calls.contains(&c) => return json!({ "type": "none" }),
+            Call::Ron => return json!({ "type": "hora", "actor": s.seat, "target": from, "pai": pai }),
+            Call::Chi(i) => ("chi", (i..i + 3).filter(|j| *j != tile).collect()),
+            Call::Pon => ("pon", vec![tile, tile]),
+            _ => return json!({ "type": "none" }),
+        };
+        json!({ "type": kind, "actor": s.seat, "target": from, "pai": pai, "consumed": s.names(&consumed) })
+    }
+}
+
+// answers each JSON line of `reader` with a line to `writer` until the game ends.
+pub fn run<R: BufRead, W: Write>(bot: &mut Bot, reader: R, mut writer: W) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let event: Value = serde_json::from_str(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let response = bot
+            .respond(&event)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        match response {
+            Some(response) => {
+                writeln!(writer, "{}", response)?;
+                writer.flush()?;
+            }
+            None => break,
+        }
+    }
+    Ok(())
+}
+
+// plays a game on an mjai server.
+pub fn connect<A: net::ToSocketAddrs>(bot: &mut Bot, addr: A) -> io::Result<()> {
+    let stream = net::TcpStream::connect(addr)?;
+    run(bot, io::BufReader::new(stream.try_clone()?), stream)
+}
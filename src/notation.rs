@@ -0,0 +1,224 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::tile::*;
+use std::fmt::Write;
+use std::*;
+
+const MJAI_HONORS: [&str; 7] = ["E", "S", "W", "N", "P", "F", "C"];
+
+const LETTER_HONORS: [char; 7] = ['E', 'S', 'W', 'N', 'P', 'F', 'C'];
+
+#[rustfmt::skip]
+const UNICODE_TILES: [char; 34] = [
+    '🀇', '🀈', '🀉', '🀊', '🀋', '🀌', '🀍', '🀎', '🀏',
+    '🀙', '🀚', '🀛', '🀜', '🀝', '🀞', '🀟', '🀠', '🀡',
+    '🀐', '🀑', '🀒', '🀓', '🀔', '🀕', '🀖', '🀗', '🀘',
+    '🀀', '🀁', '🀂', '🀃', '🀆', '🀅', '🀄',
+];
+
+fn add_tile(hand: &mut TileSet, pos: usize, tile: usize) -> Result<(), ParseError> {
+    hand.add(tile).map_err(|_| ParseError {
+        pos,
+        kind: ParseErrorKind::TooManyCopies(tile),
+    })
+}
+
+// Tenhou's 136 tile IDs: 4 * tile + copy, where copy 0 of each five is the red one.
+pub fn parse_tenhou136(ids: &[u32]) -> Result<TileSet, ParseError> {
+    let mut hand = TileSet::new();
+    let mut is_seen = [false; 4 * N_KINDS];
+    for (pos, id) in ids.iter().enumerate() {
+        let tile = *id as usize / 4;
+        if tile >= N_KINDS {
+            return Err(ParseError {
+                pos,
+                kind: ParseErrorKind::UnknownTile,
+            });
+        }
+        if mem::replace(&mut is_seen[*id as usize], true) {
+            return Err(ParseError {
+                pos,
+                kind: ParseErrorKind::DuplicateId(*id),
+            });
+        }
+        add_tile(&mut hand, pos, tile)?;
+    }
+    Ok(hand)
+}
+
+// the fives of `hand` in `reds` are red and take copy 0, and the others take copy 1 on, so that copy 0 is used for a
+// plain five only as the fourth one.
+pub fn format_tenhou136(hand: &TileSet, reds: &TileSet) -> Vec<u32> {
+    let mut ids = Vec::with_capacity(hand.count());
+    for (i, n) in hand.iter().enumerate() {
+        let is_plain_five = i < 27 && i % 9 == 4 && reds.tile(i) == 0;
+        for k in 0..*n as u32 {
+            let copy = if is_plain_five { (k + 1) % 4 } else { k };
+            ids.push(4 * i as u32 + copy);
+        }
+    }
+    ids
+}
+
+// red fives ("5mr") are read as ordinary fives, and no other tile can be red.
+pub fn parse_mjai_tile(text: &str) -> Option<usize> {
+    if let Some(i) = MJAI_HONORS.iter().position(|e| *e == text) {
+        return Some(27 + i);
+    }
+    let (text, is_red) = match text.strip_suffix('r') {
+        Some(t) => (t, true),
+        None => (text, false),
+    };
+    let mut chars = text.chars();
+    let i = chars.next()?.to_digit(10)? as usize;
+    let t = match chars.next()? {
+        'm' => 0,
+        'p' => 1,
+        's' => 2,
+        _ => return None,
+    };
+    if i == 0 || (is_red && i != 5) || chars.next().is_some() {
+        return None;
+    }
+    Some(9 * t + i - 1)
+}
+
+pub fn format_mjai_tile(tile: usize) -> String {
+    match tile {
+        0..=26 => format_tile(tile),
+        27..=33 => MJAI_HONORS[tile - 27].to_string(),
+        _ => panic!(),
+    }
+}
+
+pub fn parse_mjai_tile_set(text: &str) -> Result<TileSet, ParseError> {
+    let mut hand = TileSet::new();
+    let mut pos = 0;
+    for token in text.split(|c: char| c.is_whitespace() || c == ',') {
+        if !token.is_empty() {
+            let tile = parse_mjai_tile(token).ok_or(ParseError {
+                pos,
+                kind: ParseErrorKind::UnknownTile,
+            })?;
+            add_tile(&mut hand, pos, tile)?;
+        }
+        pos += token.chars().count() + 1;
+    }
+    Ok(hand)
+}
+
+pub fn format_mjai_tile_set(hand: &TileSet) -> String {
+    let mut tiles = Vec::with_capacity(hand.count());
+    for (i, n) in hand.iter().enumerate() {
+        for _ in 0..*n {
+            tiles.push(format_mjai_tile(i));
+        }
+    }
+    tiles.join(" ")
+}
+
+pub fn format_unicode_tile(tile: usize) -> char {
+    UNICODE_TILES[tile]
+}
+
+pub fn parse_unicode_tile_set(text: &str) -> Result<TileSet, ParseError> {
+    let mut hand = TileSet::new();
+    for (pos, c) in text.chars().enumerate() {
+        match UNICODE_TILES.iter().position(|e| *e == c) {
+            Some(tile) => add_tile(&mut hand, pos, tile)?,
+            // U+FE0F may follow U+1F004 to request the emoji presentation.
+            None if c.is_whitespace() || c == '\u{fe0f}' => (),
+            None => {
+                return Err(ParseError {
+                    pos,
+                    kind: ParseErrorKind::UnknownTile,
+                })
+            }
+        }
+    }
+    Ok(hand)
+}
+
+pub fn format_unicode_tile_set(hand: &TileSet) -> String {
+    let mut buf = String::new();
+    for (i, n) in hand.iter().enumerate() {
+        for _ in 0..*n {
+            buf.push(UNICODE_TILES[i]);
+        }
+    }
+    buf
+}
+
+// "mps" digits as in `parse_tile_set` and honors as English letters, e.g. "123m456pEESC".
+pub fn parse_letter_tile_set(text: &str) -> Result<TileSet, ParseError> {
+    let mut hand = TileSet::new();
+    let mut nums = Vec::new();
+    for (pos, c) in text.chars().enumerate() {
+        match c {
+            '1'..='9' => nums.push((pos, c.to_digit(10).unwrap() as usize)),
+            '0' => {
+                return Err(ParseError {
+                    pos,
+                    kind: ParseErrorKind::RedFive,
+                })
+            }
+            'm' | 'p' | 's' => {
+                let t = ['m', 'p', 's'].iter().position(|e| *e == c).unwrap();
+                for (pos, i) in nums.drain(..) {
+                    add_tile(&mut hand, pos, 9 * t + i - 1)?;
+                }
+            }
+            ' ' | '\t' | '\n' => (),
+            _ => match LETTER_HONORS.iter().position(|e| *e == c) {
+                Some(_) if !nums.is_empty() => {
+                    return Err(ParseError {
+                        pos: nums[0].0,
+                        kind: ParseErrorKind::DanglingDigits,
+                    })
+                }
+                Some(i) => add_tile(&mut hand, pos, 27 + i)?,
+                None => {
+                    return Err(ParseError {
+                        pos,
+                        kind: ParseErrorKind::UnknownSuit(c),
+                    })
+                }
+            },
+        }
+    }
+    if let Some((pos, _)) = nums.first() {
+        return Err(ParseError {
+            pos: *pos,
+            kind: ParseErrorKind::DanglingDigits,
+        });
+    }
+    Ok(hand)
+}
+
+pub fn format_letter_tile_set(hand: &TileSet) -> String {
+    let mut buf = String::new();
+    for t in 0..3 {
+        let mut is_empty = true;
+        for i in 0..9 {
+            for _ in 0..hand.simple(t, i) {
+                write!(buf, "{}", i + 1).unwrap();
+                is_empty = false;
+            }
+        }
+        if !is_empty {
+            write!(buf, "{} ", ['m', 'p', 's'][t]).unwrap();
+        }
+    }
+
+    let mut is_empty = true;
+    for (i, c) in LETTER_HONORS.iter().enumerate() {
+        for _ in 0..hand.honor(i) {
+            buf.push(*c);
+            is_empty = false;
+        }
+    }
+    if !is_empty {
+        buf.push(' ');
+    }
+
+    buf
+}
@@ -1,9 +1,39 @@
 // (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::score::*;
+use crate::shanten;
 use crate::tile::*;
+use crate::wait;
+use rand::SeedableRng;
 use rayon::prelude::*;
 use std::*;
 
-pub fn playout<R: rand::Rng>(hand: &TileSet, wall: &TileSet, n_samples: usize, rng: &mut R) -> f64 {
+pub const RIICHI_DEPOSIT: f64 = 1000.0;
+
+// the # of our own draws left and the probability that an opponent wins before each of them, which ends the round.
+#[derive(Clone, Copy, Debug)]
+pub struct Horizon {
+    pub n_draws: usize,
+    pub end_rate: f64,
+}
+
+impl Horizon {
+    pub fn new(n_draws: usize) -> Self {
+        Horizon { n_draws, end_rate: 0.0 }
+    }
+
+    // the # of draws we get before the round ends.
+    pub fn sample<R: rand::Rng>(&self, rng: &mut R) -> usize {
+        if self.end_rate <= 0.0 {
+            return self.n_draws;
+        }
+        (0..self.n_draws)
+            .find(|_| rng.gen::<f64>() < self.end_rate)
+            .unwrap_or(self.n_draws)
+    }
+}
+
+// a hand which does not win within the `n_draws` draws counts as `n_draws` turns.
+pub fn playout<R: rand::Rng>(hand: &TileSet, wall: &TileSet, n_draws: usize, n_samples: usize, rng: &mut R) -> f64 {
     let n_tiles = hand.count() + 1;
     let mut acc = Vec::new();
     for i in 0..wall.len() {
@@ -11,18 +41,20 @@ pub fn playout<R: rand::Rng>(hand: &TileSet, wall: &TileSet, n_samples: usize, r
             acc.push(i);
         }
     }
+    let n_draws = cmp::min(n_draws, acc.len());
     let mut sum = 0;
     for _ in 0..n_samples {
-        // XXX
-        rand::seq::SliceRandom::shuffle(&mut acc[..], rng);
+        let (live, _) = rand::seq::SliceRandom::partial_shuffle(&mut acc[..], rng, n_draws);
         let mut hand = hand.clone();
-        for i in 0.. {
-            *hand.tile_mut(acc[i]) += 1;
+        let mut n_turns = n_draws;
+        for (i, tile) in live.iter().enumerate() {
+            *hand.tile_mut(*tile) += 1;
             if count_head_and_triad(&mut hand, false) >= n_tiles {
-                sum += i;
+                n_turns = i;
                 break;
             }
         }
+        sum += n_turns;
     }
     sum as f64 / n_samples as f64
 }
@@ -30,12 +62,16 @@ pub fn playout<R: rand::Rng>(hand: &TileSet, wall: &TileSet, n_samples: usize, r
 pub fn draw_tile<R: rand::Rng>(
     hand: &mut TileSet,
     wall: &mut TileSet,
+    n_draws: usize,
     depth: usize,
     n_samples: usize,
     rng: &mut R,
 ) -> f64 {
     if depth == 0 {
-        return playout(hand, wall, n_samples, rng);
+        return playout(hand, wall, n_draws, n_samples, rng);
+    }
+    if n_draws == 0 {
+        return 0.0;
     }
 
     let n_samples = cmp::max(n_samples / wall.count(), 1);
@@ -44,7 +80,14 @@ pub fn draw_tile<R: rand::Rng>(
         if wall.tile(i) > 0 {
             *wall.tile_mut(i) -= 1;
             *hand.tile_mut(i) += 1;
-            let (score, _) = discard_tile(hand, wall, depth - 1, wall.tile(i) as usize * n_samples, rng);
+            let (score, _) = discard_tile(
+                hand,
+                wall,
+                n_draws - 1,
+                depth - 1,
+                wall.tile(i) as usize * n_samples,
+                rng,
+            );
             *hand.tile_mut(i) -= 1;
             *wall.tile_mut(i) += 1;
             sum += score * wall.tile(i) as f64;
@@ -56,6 +99,7 @@ pub fn draw_tile<R: rand::Rng>(
 pub fn discard_tile<R: rand::Rng>(
     hand: &mut TileSet,
     wall: &mut TileSet,
+    n_draws: usize,
     depth: usize,
     n_samples: usize,
     rng: &mut R,
@@ -73,7 +117,7 @@ pub fn discard_tile<R: rand::Rng>(
     for i in 0..hand.len() {
         if hand.tile(i) > 0 {
             *hand.tile_mut(i) -= 1;
-            let score = draw_tile(hand, wall, depth, n_samples, rng) + 1.0;
+            let score = draw_tile(hand, wall, n_draws, depth, n_samples, rng) + 1.0;
             *hand.tile_mut(i) += 1;
             discards.push((i, score));
             best_score = f64::min(best_score, score);
@@ -82,11 +126,14 @@ pub fn discard_tile<R: rand::Rng>(
     (best_score, discards)
 }
 
+// the samplers of the discards are seeded from `seed`, so that the results do not depend on the threads.
 pub fn discard_tile_parallel(
     hand: &mut TileSet,
     wall: &mut TileSet,
+    n_draws: usize,
     depth: usize,
     n_samples: usize,
+    seed: u64,
 ) -> (f64, Vec<(usize, f64)>) {
     let count = count_head_and_triad(hand, false);
     if count == hand.count() {
@@ -104,7 +151,8 @@ pub fn discard_tile_parallel(
             }
             let mut hand = hand.clone();
             *hand.tile_mut(i) -= 1;
-            let score = draw_tile(&mut hand, &mut wall.clone(), depth, n_samples, &mut rand::thread_rng()) + 1.0;
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+            let score = draw_tile(&mut hand, &mut wall.clone(), n_draws, depth, n_samples, &mut rng) + 1.0;
             *hand.tile_mut(i) += 1;
             Some((i, score))
         })
@@ -113,3 +161,273 @@ pub fn discard_tile_parallel(
     let best_score = discards.iter().fold(f64::MAX, |r, e| r.min(e.1));
     (best_score, discards)
 }
+
+// the complete subsets of `hand` of `n_tiles` tiles, as a pair and triads, which contain `last`, the tile which
+// completed `hand`.
+pub fn winning_subsets(hand: &TileSet, last: usize, n_tiles: usize) -> Vec<TileSet> {
+    struct Search {
+        hand: TileSet,
+        subset: TileSet,
+        last: usize,
+        dst: Vec<TileSet>,
+    }
+
+    // `n_left` is the # of tiles left in `hand`, all from `i0`.
+    fn visit(s: &mut Search, i0: usize, n_left: usize, n_triads: usize, has_pair: bool) {
+        if n_triads == 0 && has_pair {
+            if !s.dst.contains(&s.subset) {
+                s.dst.push(s.subset.clone());
+            }
+            return;
+        }
+        let i = match (i0..s.hand.len()).find(|i| s.hand.tile(*i) > 0) {
+            Some(i) => i,
+            None => return,
+        };
+        if n_left < 3 * n_triads + 2 * !has_pair as usize || (i > s.last && s.subset.tile(s.last) == 0) {
+            return;
+        }
+        let is_run = i < 27 && i % 9 < 7 && s.hand.tile(i + 1) > 0 && s.hand.tile(i + 2) > 0;
+        let (pair, triplet, run) = ([i, i], [i, i, i], [i, i + 1, i + 2]);
+        // (whether the block can be taken, its tiles, # of triads, whether the pair is taken after it).
+        let blocks: [(bool, &[usize], usize, bool); 3] = [
+            (!has_pair && s.hand.tile(i) >= 2, &pair, 0, true),
+            (n_triads > 0 && s.hand.tile(i) >= 3, &triplet, 1, has_pair),
+            (n_triads > 0 && is_run, &run, 1, has_pair),
+        ];
+        for (is_possible, tiles, n, has_pair) in blocks {
+            if !is_possible {
+                continue;
+            }
+            for t in tiles {
+                *s.hand.tile_mut(*t) -= 1;
+                *s.subset.tile_mut(*t) += 1;
+            }
+            visit(s, i, n_left - tiles.len(), n_triads - n, has_pair);
+            for t in tiles {
+                *s.hand.tile_mut(*t) += 1;
+                *s.subset.tile_mut(*t) -= 1;
+            }
+        }
+        // the rest of the copies of `i` are left out.
+        let n = s.hand.tile(i);
+        *s.hand.tile_mut(i) = 0;
+        visit(s, i + 1, n_left - n as usize, n_triads, has_pair);
+        *s.hand.tile_mut(i) = n;
+    }
+
+    if hand.count() == n_tiles {
+        return vec![hand.clone()];
+    }
+    let mut s = Search {
+        hand: hand.clone(),
+        subset: TileSet::new(),
+        last,
+        dst: Vec::new(),
+    };
+    visit(&mut s, 0, hand.count(), (n_tiles - 2) / 3, false);
+    s.dst.retain(|h| h.tile(last) > 0);
+    s.dst
+}
+
+// the best score of `hand` read as any of its complete subsets, as the tiles drawn after the wait are discarded.
+pub fn best_score(hand: &TileSet, melds: &[Meld], last: usize, n_tiles: usize, ctx: &WinContext) -> Option<Score> {
+    winning_subsets(hand, last, n_tiles)
+        .iter()
+        .filter_map(|h| score_hand(h, melds, last, ctx))
+        .max_by_key(|s| s.points)
+}
+
+// returns (win rate, mean points) within the draws of `horizon`.  a hand without yaku keeps drawing.
+pub fn playout_value<R: rand::Rng>(
+    hand: &TileSet,
+    melds: &[Meld],
+    wall: &TileSet,
+    horizon: &Horizon,
+    ctx: &WinContext,
+    n_samples: usize,
+    rng: &mut R,
+) -> (f64, f64) {
+    let n_tiles = hand.count() + 1;
+    let mut acc = Vec::new();
+    for i in 0..wall.len() {
+        for _ in 0..wall.tile(i) {
+            acc.push(i);
+        }
+    }
+    let n_draws = cmp::min(horizon.n_draws, acc.len());
+    let mut ctx = ctx.clone();
+    ctx.win_type = WinType::Tsumo;
+    let mut n_wins = 0;
+    let mut sum = 0;
+    for _ in 0..n_samples {
+        let n = cmp::min(horizon.sample(rng), n_draws);
+        let (draws, _) = rand::seq::SliceRandom::partial_shuffle(&mut acc[..], rng, n);
+        let mut hand = hand.clone();
+        for tile in draws.iter() {
+            *hand.tile_mut(*tile) += 1;
+            if count_head_and_triad(&mut hand, false) < n_tiles {
+                continue;
+            }
+            if let Some(score) = best_score(&hand, melds, *tile, n_tiles, &ctx) {
+                n_wins += 1;
+                sum += score.points;
+                break;
+            }
+        }
+    }
+    (n_wins as f64 / n_samples as f64, sum as f64 / n_samples as f64)
+}
+
+// returns (discarded tile, win rate, mean points) for each discard.  the samplers are seeded as
+// `discard_tile_parallel()`.
+pub fn discard_tile_value_parallel(
+    hand: &mut TileSet,
+    melds: &[Meld],
+    wall: &mut TileSet,
+    horizon: &Horizon,
+    ctx: &WinContext,
+    n_samples: usize,
+    seed: u64,
+) -> Vec<(usize, f64, f64)> {
+    let count = count_head_and_triad(hand, false);
+    if count == hand.count() {
+        return Vec::new();
+    }
+
+    let n = hand.iter().filter(|i| **i > 0).count();
+    let n_samples = cmp::max(n_samples / n, 1);
+    (0..hand.len())
+        .into_par_iter()
+        .filter_map(|i| {
+            if hand.tile(i) == 0 {
+                return None;
+            }
+            let mut hand = hand.clone();
+            *hand.tile_mut(i) -= 1;
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+            let (rate, points) = playout_value(&hand, melds, wall, horizon, ctx, n_samples, &mut rng);
+            Some((i, rate, points))
+        })
+        .collect()
+}
+
+// returns (win rate, mean points) of a tenpai hand which keeps its wait and discards every draw.  with `ctx.riichi`,
+// a win on the first draw gets ippatsu, the ura dora are drawn from the rest of the wall and the deposit is lost
+// when the hand does not win.
+pub fn playout_locked<R: rand::Rng>(
+    hand: &TileSet,
+    melds: &[Meld],
+    wall: &TileSet,
+    horizon: &Horizon,
+    ctx: &WinContext,
+    n_samples: usize,
+    rng: &mut R,
+) -> (f64, f64) {
+    let riichi = ctx.riichi;
+    let mut is_winning = [false; N_KINDS];
+    for w in wait::winning_tiles(hand, wall) {
+        is_winning[w.tile] = true;
+    }
+    let mut acc = Vec::new();
+    for i in 0..wall.len() {
+        for _ in 0..wall.tile(i) {
+            acc.push(i);
+        }
+    }
+    let n_ura = if riichi { ctx.dora_indicators.len() } else { 0 };
+    let n_draws = cmp::min(horizon.n_draws, acc.len().saturating_sub(n_ura));
+    let mut ctx = ctx.clone();
+    ctx.win_type = WinType::Tsumo;
+    let mut n_wins = 0;
+    let mut sum = 0.0;
+    for _ in 0..n_samples {
+        let (draws, _) = rand::seq::SliceRandom::partial_shuffle(&mut acc[..], rng, n_draws + n_ura);
+        let (draws, ura) = draws.split_at(n_draws);
+        let n = cmp::min(horizon.sample(rng), n_draws);
+        let score = draws[..n]
+            .iter()
+            .enumerate()
+            .find(|(_, t)| is_winning[**t])
+            .and_then(|(i, t)| {
+                let mut hand = hand.clone();
+                *hand.tile_mut(*t) += 1;
+                ctx.ippatsu = riichi && i == 0;
+                ctx.ura_indicators = ura.to_vec();
+                score_hand(&hand, melds, *t, &ctx)
+            });
+        match score {
+            Some(s) => {
+                n_wins += 1;
+                sum += s.points as f64;
+            }
+            None if riichi => sum -= RIICHI_DEPOSIT,
+            None => (),
+        }
+    }
+    (n_wins as f64 / n_samples as f64, sum / n_samples as f64)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct RiichiComparison {
+    // (win rate, mean points); `None` for an open hand.
+    pub riichi: Option<(f64, f64)>,
+    pub dama: (f64, f64),
+    // keeps changing tiles, i.e. `playout_value()`.
+    pub improve: (f64, f64),
+}
+
+pub fn compare_riichi<R: rand::Rng>(
+    hand: &TileSet,
+    melds: &[Meld],
+    wall: &TileSet,
+    horizon: &Horizon,
+    ctx: &WinContext,
+    n_samples: usize,
+    rng: &mut R,
+) -> RiichiComparison {
+    let is_menzen = melds.iter().all(|m| !m.is_open());
+    let mut riichi_ctx = ctx.clone();
+    riichi_ctx.riichi = true;
+    let mut dama_ctx = ctx.clone();
+    dama_ctx.riichi = false;
+    RiichiComparison {
+        riichi: if is_menzen {
+            Some(playout_locked(hand, melds, wall, horizon, &riichi_ctx, n_samples, rng))
+        } else {
+            None
+        },
+        dama: playout_locked(hand, melds, wall, horizon, &dama_ctx, n_samples, rng),
+        improve: playout_value(hand, melds, wall, horizon, ctx, n_samples, rng),
+    }
+}
+
+// compares riichi, dama and improving for each discard which leaves the hand in tenpai.  the samplers are seeded as
+// `discard_tile_parallel()`.
+pub fn discard_tile_riichi_parallel(
+    hand: &TileSet,
+    melds: &[Meld],
+    wall: &TileSet,
+    horizon: &Horizon,
+    ctx: &WinContext,
+    n_samples: usize,
+    seed: u64,
+) -> Vec<(usize, RiichiComparison)> {
+    let n = hand.iter().filter(|i| **i > 0).count();
+    let n_samples = cmp::max(n_samples / n, 1);
+    (0..hand.len())
+        .into_par_iter()
+        .filter_map(|i| {
+            if hand.tile(i) == 0 {
+                return None;
+            }
+            let mut hand = hand.clone();
+            *hand.tile_mut(i) -= 1;
+            if shanten::shanten(&hand) != 0 {
+                return None;
+            }
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+            Some((i, compare_riichi(&hand, melds, wall, horizon, ctx, n_samples, &mut rng)))
+        })
+        .collect()
+}
@@ -0,0 +1,221 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+// the Python module, built with `--features python`.  the tiles are indices of 0..34 and the counts are lists of the
+// 34 kinds, which numpy.array() takes as they are.  the engines release the GIL while they run.
+// the conversions of the errors are in the code generated by pyo3.
+#![allow(clippy::useless_conversion)]
+use crate::danger;
+use crate::playout;
+use crate::score::*;
+use crate::search;
+use crate::shanten;
+use crate::table::*;
+use crate::tile::*;
+use crate::uct;
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+use rand::SeedableRng;
+use std::*;
+
+// (tile, riichi, mean # of turns to win, mean points, # of visits) of UCT.
+type UctDiscard = (usize, bool, f64, f64, usize);
+
+// a set of tiles, as a sequence of the # of each of the 34 kinds.
+#[pyclass(name = "TileSet")]
+#[derive(Clone)]
+pub struct PyTileSet(TileSet);
+
+fn check_tile(tile: usize) -> PyResult<usize> {
+    if tile < N_KINDS {
+        Ok(tile)
+    } else {
+        Err(PyIndexError::new_err(format!("Tile out of range: {}", tile)))
+    }
+}
+
+#[pymethods]
+impl PyTileSet {
+    #[new]
+    #[pyo3(signature = (counts = None))]
+    fn new(counts: Option<Vec<u8>>) -> PyResult<Self> {
+        let mut set = TileSet::new();
+        if let Some(counts) = counts {
+            if counts.len() != N_KINDS {
+                return Err(PyValueError::new_err(format!("# of counts must be {}", N_KINDS)));
+            }
+            for (t, n) in counts.into_iter().enumerate() {
+                *set.tile_mut(t) = n;
+            }
+            set.validate()
+                .map_err(|e| PyValueError::new_err(format!("Invalid tiles: {}", e)))?;
+        }
+        Ok(PyTileSet(set))
+    }
+
+    fn __len__(&self) -> usize {
+        N_KINDS
+    }
+
+    fn __getitem__(&self, tile: usize) -> PyResult<u8> {
+        Ok(self.0.tile(check_tile(tile)?))
+    }
+
+    fn __setitem__(&mut self, tile: usize, n: u8) -> PyResult<()> {
+        if n > N_COPIES {
+            return Err(PyValueError::new_err(format!("More than {} copies", N_COPIES)));
+        }
+        *self.0.tile_mut(check_tile(tile)?) = n;
+        Ok(())
+    }
+
+    fn __eq__(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+
+    fn __str__(&self) -> String {
+        format_tile_set(&self.0).trim_end().to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("TileSet({:?})", self.__str__())
+    }
+
+    // the # of tiles.
+    fn count(&self) -> usize {
+        self.0.count()
+    }
+
+    fn counts(&self) -> Vec<u8> {
+        self.0.iter().copied().collect()
+    }
+
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+}
+
+// parses the notation, e.g. "123m456p789s1122z".
+#[pyfunction(name = "parse_tile_set")]
+fn py_parse_tile_set(text: &str) -> PyResult<PyTileSet> {
+    parse_tile_set(text)
+        .map(PyTileSet)
+        .map_err(|e| PyValueError::new_err(format!("Syntax error at {}: {}", e.pos, e.kind)))
+}
+
+fn check_hand(hand: &TileSet, rem: usize) -> PyResult<()> {
+    hand.validate()
+        .map_err(|e| PyValueError::new_err(format!("Invalid hand: {}", e)))?;
+    if hand.count() % 3 != rem {
+        return Err(PyValueError::new_err(format!("# of tiles must be 3n + {}", rem)));
+    }
+    Ok(())
+}
+
+// the tiles neither in the hand nor in `visible`, and the table with `visible` as the discards.
+fn unseen(hand: &TileSet, visible: Option<&PyTileSet>) -> PyResult<(TileSet, TableState)> {
+    let mut table = TableState::new();
+    if let Some(v) = visible {
+        table.discards = v.0.clone();
+    }
+    let wall = table
+        .wall(hand)
+        .map_err(|e| PyValueError::new_err(format!("Invalid hand: {}", e)))?;
+    Ok((wall, table))
+}
+
+// the shanten number of a hand of 3n + 1 or 3n + 2 tiles, which is -1 if it is complete.
+#[pyfunction(name = "shanten")]
+fn py_shanten(hand: &PyTileSet) -> PyResult<i32> {
+    hand.0
+        .validate()
+        .map_err(|e| PyValueError::new_err(format!("Invalid hand: {}", e)))?;
+    if hand.0.count().is_multiple_of(3) {
+        return Err(PyValueError::new_err("# of tiles must not be 3n"));
+    }
+    Ok(shanten::shanten(&hand.0) as i32)
+}
+
+// the # of each of the 34 kinds which lower the shanten number of a hand of 3n + 1 tiles, out of the tiles neither in
+// the hand nor in `visible`.
+#[pyfunction(name = "ukeire")]
+#[pyo3(signature = (hand, visible = None))]
+fn py_ukeire(hand: &PyTileSet, visible: Option<&PyTileSet>) -> PyResult<Vec<usize>> {
+    check_hand(&hand.0, 1)?;
+    let (wall, _) = unseen(&hand.0, visible)?;
+    let mut counts = vec![0; N_KINDS];
+    for (t, n) in shanten::ukeire(&hand.0, &wall) {
+        counts[t] = n;
+    }
+    Ok(counts)
+}
+
+// the discards of a hand of 3n + 2 tiles by the search, as (tile, min-mean # of non-meld tiles) from the best.
+#[pyfunction(name = "search")]
+#[pyo3(signature = (hand, visible = None, depth = 2))]
+fn py_search(py: Python, hand: &PyTileSet, visible: Option<&PyTileSet>, depth: usize) -> PyResult<Vec<(usize, f64)>> {
+    check_hand(&hand.0, 2)?;
+    let (mut wall, _) = unseen(&hand.0, visible)?;
+    let mut hand = hand.0.clone();
+    let (_, mut scores) = py.allow_threads(|| search::discard_tile_parallel(&mut hand, &mut wall, depth));
+    scores.sort_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap());
+    Ok(scores)
+}
+
+// the discards of a hand of 3n + 2 tiles by the playouts, as (tile, mean # of turns to win) from the best.
+#[pyfunction(name = "playout")]
+#[pyo3(signature = (hand, visible = None, depth = 2, n_samples = 1 << 16, seed = 0))]
+fn py_playout(
+    py: Python,
+    hand: &PyTileSet,
+    visible: Option<&PyTileSet>,
+    depth: usize,
+    n_samples: usize,
+    seed: u64,
+) -> PyResult<Vec<(usize, f64)>> {
+    check_hand(&hand.0, 2)?;
+    let (mut wall, table) = unseen(&hand.0, visible)?;
+    let n_draws = table.n_draws(&wall);
+    let mut hand = hand.0.clone();
+    let (_, mut scores) =
+        py.allow_threads(|| playout::discard_tile_parallel(&mut hand, &mut wall, n_draws, depth, n_samples, seed));
+    scores.sort_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap());
+    Ok(scores)
+}
+
+// the discards of a hand of 3n + 2 tiles by UCT, from the most visited.
+#[pyfunction(name = "uct")]
+#[pyo3(signature = (hand, visible = None, n_samples = 1 << 16, seed = 0))]
+fn py_uct(
+    py: Python,
+    hand: &PyTileSet,
+    visible: Option<&PyTileSet>,
+    n_samples: usize,
+    seed: u64,
+) -> PyResult<Vec<UctDiscard>> {
+    check_hand(&hand.0, 2)?;
+    let (wall, table) = unseen(&hand.0, visible)?;
+    let horizon = danger::horizon(&table, &wall);
+    let ctx = table.win_context(WinType::Tsumo);
+    let hand = &hand.0;
+    let mut actions = py.allow_threads(|| {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        uct::discard_tile(hand, &[], &wall, &horizon, &ctx, n_samples, &mut rng)
+    });
+    actions.sort_by_key(|a| cmp::Reverse(a.count));
+    Ok(actions
+        .into_iter()
+        .map(|a| (a.tile, a.riichi, a.turns, a.points, a.count))
+        .collect())
+}
+
+#[pymodule]
+fn teff(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("N_KINDS", N_KINDS)?;
+    m.add_class::<PyTileSet>()?;
+    m.add_function(wrap_pyfunction!(py_parse_tile_set, m)?)?;
+    m.add_function(wrap_pyfunction!(py_shanten, m)?)?;
+    m.add_function(wrap_pyfunction!(py_ukeire, m)?)?;
+    m.add_function(wrap_pyfunction!(py_search, m)?)?;
+    m.add_function(wrap_pyfunction!(py_playout, m)?)?;
+    m.add_function(wrap_pyfunction!(py_uct, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,211 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::bench;
+use crate::danger;
+use crate::score::*;
+use crate::table::*;
+use crate::tile::*;
+use crate::uct;
+use std::io::prelude::*;
+use std::*;
+
+const HELP: &str = "\
+Commands:
+  hand <tiles>       starts over with a hand
+  draw <tile>
+  discard <tile>
+  see <tiles>        tiles seen elsewhere, e.g. the discards of the opponents
+  undo
+  think [<n>]        adds samples to the UCT tree (--samples)
+  analyze            analyzes the hand by every engine
+  show
+  quit
+";
+
+// an interactive session on a hand.  the UCT tree is kept across a discard and the next draw, which only narrow the
+// wall down, and is rebuilt when other tiles are seen.
+struct Session<'a> {
+    hand: TileSet,
+    table: TableState,
+    params: &'a bench::Params,
+    // the hands and the tables before each command, for undo.
+    history: Vec<(TileSet, TableState)>,
+    tree: Option<uct::Tree>,
+    // the discard from the hand of `tree`.
+    discard: Option<usize>,
+}
+
+fn parse_tiles(text: &str) -> Result<TileSet, String> {
+    parse_tile_set(text).map_err(|e| format!("Syntax error at {}: {}", e.pos, e.kind))
+}
+
+fn parse_tile(text: &str) -> Result<usize, String> {
+    let tiles = parse_tiles(text)?;
+    match tiles.iter().position(|n| *n > 0) {
+        Some(t) if tiles.count() == 1 => Ok(t),
+        _ => Err(format!("Not a tile: {}", text)),
+    }
+}
+
+impl<'a> Session<'a> {
+    fn execute<R: rand::Rng>(&mut self, command: &str, arg: Option<&str>, rng: &mut R) -> Result<(), String> {
+        let mut hand = self.hand.clone();
+        let mut table = self.table.clone();
+        // the tile drawn or discarded.
+        let mut step = None;
+        match (command, arg) {
+            ("hand", Some(text)) => {
+                hand = parse_tiles(text)?;
+                table.own_discards = TileSet::new();
+            }
+            ("draw", Some(text)) => {
+                let t = parse_tile(text)?;
+                if hand.count() % 3 != 1 {
+                    return Err("# of tiles must be 3n + 1 to draw".to_string());
+                }
+                *hand.tile_mut(t) += 1;
+                step = Some(t);
+            }
+            ("discard", Some(text)) => {
+                let t = parse_tile(text)?;
+                if hand.count() % 3 != 2 {
+                    return Err("# of tiles must be 3n + 2 to discard".to_string());
+                }
+                if hand.tile(t) == 0 {
+                    return Err(format!("No {} in the hand", format_tile(t)));
+                }
+                *hand.tile_mut(t) -= 1;
+                *table.own_discards.tile_mut(t) += 1;
+                step = Some(t);
+            }
+            ("see", Some(text)) => {
+                let tiles = parse_tiles(text)?;
+                table.discards = table
+                    .discards
+                    .union(&tiles)
+                    .map_err(|e| format!("Invalid tiles: {}", e))?;
+            }
+            ("undo", None) => {
+                let (hand, table) = self.history.pop().ok_or("Nothing to undo")?;
+                self.update(hand, table, None, None);
+                self.show(rng);
+                return Ok(());
+            }
+            ("think", _) => {
+                let n_samples = match arg {
+                    Some(t) => t.parse().map_err(|_| format!("Not a number: {}", t))?,
+                    None => self.params.n_samples,
+                };
+                return self.think(n_samples, rng);
+            }
+            ("analyze", None) => {
+                let mut hand = self.hand.clone();
+                bench::analyze_hand(&mut hand, &self.table, self.params, bench::Format::Text, rng)?;
+                return Ok(());
+            }
+            ("show", None) => {
+                self.show(rng);
+                return Ok(());
+            }
+            ("help", None) => {
+                print!("{}", HELP);
+                return Ok(());
+            }
+            _ => return Err(format!("Unknown command or argument: {}", command)),
+        }
+        table.wall(&hand).map_err(|e| format!("Invalid hand: {}", e))?;
+
+        let (tree, discard) = match (command, step) {
+            ("discard", Some(t)) => (self.tree.take(), Some(t)),
+            ("draw", Some(t)) => match (self.tree.take(), self.discard) {
+                (Some(mut tree), Some(d)) => {
+                    tree.advance(d, t);
+                    (Some(tree), None)
+                }
+                _ => (None, None),
+            },
+            _ => (None, None),
+        };
+        self.history.push((self.hand.clone(), self.table.clone()));
+        self.update(hand, table, tree, discard);
+        self.show(rng);
+        Ok(())
+    }
+
+    fn update(&mut self, hand: TileSet, table: TableState, tree: Option<uct::Tree>, discard: Option<usize>) {
+        self.hand = hand;
+        self.table = table;
+        self.tree = tree;
+        self.discard = discard;
+    }
+
+    // the shanten number and ukeire, and the discards by UCT with the samples topped up to `--samples`.
+    fn show<R: rand::Rng>(&mut self, rng: &mut R) {
+        bench::analyze_shanten(&self.hand, &self.table);
+        if self.hand.count() % 3 == 2 && self.params.uses("uct") {
+            let n_samples = self.tree.as_ref().map_or(0, |t| t.n_samples());
+            let _ = self.think(self.params.n_samples.saturating_sub(n_samples), rng);
+        }
+    }
+
+    fn think<R: rand::Rng>(&mut self, n_samples: usize, rng: &mut R) -> Result<(), String> {
+        if self.hand.count() % 3 != 2 {
+            return Err("# of tiles must be 3n + 2 to think".to_string());
+        }
+        if self.tree.is_none() {
+            let wall = self
+                .table
+                .wall(&self.hand)
+                .map_err(|e| format!("Invalid hand: {}", e))?;
+            let horizon = danger::horizon(&self.table, &wall);
+            let ctx = self.table.win_context(WinType::Tsumo);
+            self.tree = Some(uct::Tree::new(&self.hand, &[], &wall, &horizon, &ctx));
+        }
+        let tree = self.tree.as_mut().unwrap();
+        tree.sample(n_samples, rng);
+        let mut discards = tree.actions();
+        if !discards.is_empty() {
+            discards.sort_by_key(|a| cmp::Reverse(a.count));
+            bench::print_uct(&discards, tree.n_samples());
+            println!();
+        }
+        Ok(())
+    }
+}
+
+// reads the commands from `input` until it ends or "quit", printing the analyses of each state.
+pub fn run<R: rand::Rng>(
+    input: impl BufRead,
+    hand: Option<TileSet>,
+    table: &TableState,
+    params: &bench::Params,
+    rng: &mut R,
+) -> io::Result<()> {
+    let mut session = Session {
+        hand: hand.clone().unwrap_or_default(),
+        table: table.clone(),
+        params,
+        history: Vec::new(),
+        tree: None,
+        discard: None,
+    };
+    if hand.is_some() {
+        session.show(rng);
+    }
+    for line in input.lines() {
+        let line = line?;
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some("quit") | Some("exit") => break,
+            Some(c) => c,
+            None => continue,
+        };
+        // the tiles may be split by spaces.
+        let arg: String = words.collect();
+        let arg = if arg.is_empty() { None } else { Some(arg.as_str()) };
+        if let Err(e) = session.execute(command, arg, rng) {
+            println!("  {}.", e);
+        }
+        io::stdout().flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,649 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::game::{Discard, N_PLAYERS};
+use crate::table::*;
+use crate::tile::*;
+use serde_json::Value;
+use std::*;
+
+// red fives are read as ordinary fives.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Draw {
+        seat: usize,
+        tile: usize,
+    },
+    Discard {
+        seat: usize,
+        tile: usize,
+        // declares riichi with the discard.
+        riichi: bool,
+        tsumogiri: bool,
+    },
+    // chi, pon or kan on the last discard of `from`.
+    Call {
+        seat: usize,
+        from: usize,
+        meld: Meld,
+    },
+    ClosedKan {
+        seat: usize,
+        tile: usize,
+    },
+    AddedKan {
+        seat: usize,
+        tile: usize,
+    },
+    // a new dora indicator.
+    Dora(usize),
+    // a tsumo if `from == seat`.
+    Win {
+        seat: usize,
+        from: usize,
+    },
+    Ryuukyoku,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Round {
+    pub round_wind: usize,
+    pub dealer: usize,
+    pub honba: usize,
+    pub deposits: usize,
+    pub scores: [isize; N_PLAYERS],
+    pub dora_indicator: usize,
+    pub hands: Vec<TileSet>,
+    pub events: Vec<Event>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub names: Vec<String>,
+    pub rounds: Vec<Round>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    Syntax(String),
+    InvalidTile(i64),
+    // a call of the mjlog format whose tiles are out of range.
+    InvalidMeld(i64),
+    // the events do not follow the hands.
+    Tile(TileError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Syntax(e) => write!(f, "syntax error: {}", e),
+            ReplayError::InvalidTile(t) => write!(f, "invalid tile: {}", t),
+            ReplayError::InvalidMeld(m) => write!(f, "invalid meld: {}", m),
+            ReplayError::Tile(e) => write!(f, "inconsistent log: {}", e),
+        }
+    }
+}
+
+impl From<TileError> for ReplayError {
+    fn from(e: TileError) -> Self {
+        ReplayError::Tile(e)
+    }
+}
+
+fn syntax_error<T>(text: &str) -> Result<T, ReplayError> {
+    Err(ReplayError::Syntax(text.to_string()))
+}
+
+// one of our discard decisions: `hand` of 3n + 2 tiles and what we could see of the table before discarding.
+#[derive(Clone, Debug)]
+pub struct Position {
+    pub round: usize,
+    pub seat: usize,
+    pub hand: TileSet,
+    pub melds: Vec<Meld>,
+    pub table: TableState,
+    // the tile discarded in the game.
+    pub discard: usize,
+    pub riichi: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Seat {
+    hand: TileSet,
+    melds: Vec<Meld>,
+    discards: Vec<Discard>,
+    riichi: bool,
+}
+
+impl Seat {
+    fn pond(&self) -> TileSet {
+        let mut dst = TileSet::new();
+        for d in self.discards.iter().filter(|d| !d.called) {
+            *dst.tile_mut(d.tile) += 1;
+        }
+        dst
+    }
+}
+
+impl Round {
+    // the positions before each discard of `seat`, with `round` as their index.
+    pub fn positions(&self, round: usize, seat: usize) -> Result<Vec<Position>, ReplayError> {
+        let mut seats: Vec<_> = self
+            .hands
+            .iter()
+            .map(|h| Seat {
+                hand: h.clone(),
+                ..Default::default()
+            })
+            .collect();
+        let mut dora_indicators = vec![self.dora_indicator];
+        let mut dst = Vec::new();
+        for e in self.events.iter() {
+            match *e {
+                Event::Draw { seat: s, tile } => seats[s].hand.add(tile)?,
+                Event::Discard {
+                    seat: s, tile, riichi, ..
+                } => {
+                    if s == seat {
+                        dst.push(Position {
+                            round,
+                            seat,
+                            hand: seats[s].hand.clone(),
+                            melds: seats[s].melds.clone(),
+                            table: self.table(&seats, seat, &dora_indicators),
+                            discard: tile,
+                            riichi,
+                        });
+                    }
+                    seats[s].hand.remove(tile)?;
+                    seats[s].riichi |= riichi;
+                    seats[s].discards.push(Discard {
+                        tile,
+                        riichi,
+                        called: false,
+                    });
+                }
+                Event::Call { seat: s, from, meld } => {
+                    let called = match seats[from].discards.last_mut() {
+                        Some(d) => {
+                            d.called = true;
+                            d.tile
+                        }
+                        None => return syntax_error("a call without a discard"),
+                    };
+                    let mut tiles = meld.tiles();
+                    tiles.remove(called)?;
+                    seats[s].hand = seats[s].hand.difference(&tiles)?;
+                    seats[s].melds.push(meld);
+                }
+                Event::ClosedKan { seat: s, tile } => {
+                    seats[s].hand = seats[s].hand.difference(&Meld::ClosedKan(tile).tiles())?;
+                    seats[s].melds.push(Meld::ClosedKan(tile));
+                }
+                Event::AddedKan { seat: s, tile } => {
+                    seats[s].hand.remove(tile)?;
+                    for m in seats[s].melds.iter_mut().filter(|m| **m == Meld::Pon(tile)) {
+                        *m = Meld::Kan(tile);
+                    }
+                }
+                Event::Dora(tile) => dora_indicators.push(tile),
+                Event::Win { .. } | Event::Ryuukyoku => (),
+            }
+        }
+        Ok(dst)
+    }
+
+    fn table(&self, seats: &[Seat], seat: usize, dora_indicators: &[usize]) -> TableState {
+        let mut table = TableState::new();
+        table.own_discards = seats[seat].pond();
+        for (i, o) in table.opponents.iter_mut().enumerate() {
+            let p = &seats[(seat + 1 + i) % N_PLAYERS];
+            o.discards = p.pond();
+            o.riichi = p.riichi;
+        }
+        for p in seats.iter() {
+            for m in p.melds.iter() {
+                for (i, n) in m.tiles().iter().enumerate() {
+                    *table.melds.tile_mut(i) += n;
+                }
+            }
+        }
+        for i in dora_indicators.iter() {
+            *table.dora_indicators.tile_mut(*i) += 1;
+        }
+        table.opponent_tiles = (1..N_PLAYERS).map(|i| seats[(seat + i) % N_PLAYERS].hand.count()).sum();
+        table.seat_wind = (seat + N_PLAYERS - self.dealer) % N_PLAYERS;
+        table.round_wind = self.round_wind;
+        table
+    }
+}
+
+impl Replay {
+    // the positions before each discard of `seat` in every round.
+    pub fn positions(&self, seat: usize) -> Result<Vec<Position>, ReplayError> {
+        let mut dst = Vec::new();
+        for (i, r) in self.rounds.iter().enumerate() {
+            dst.extend(r.positions(i, seat)?);
+        }
+        Ok(dst)
+    }
+}
+
+// either format, told by the first character.
+pub fn parse(text: &str) -> Result<Replay, ReplayError> {
+    if text.trim_start().starts_with('<') {
+        parse_mjlog(text)
+    } else {
+        parse_tenhou6(text)
+    }
+}
+
+// the tiles are numbered 0..136 in the mjlog format.
+fn parse_tenhou136_tile(id: i64) -> Result<usize, ReplayError> {
+    match id {
+        0..=135 => Ok(id as usize / 4),
+        _ => Err(ReplayError::InvalidTile(id)),
+    }
+}
+
+fn percent_decode(text: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = text.as_bytes();
+    while let Some((&c, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| str::from_utf8(h).ok());
+        match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) if c == b'%' => {
+                bytes.push(b);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(c);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// a tag of an mjlog as (name, attributes).
+type Tag<'a> = (&'a str, Vec<(&'a str, &'a str)>);
+
+// the tags of an mjlog, which has no nesting but the root.
+fn xml_tags(text: &str) -> Result<Vec<Tag<'_>>, ReplayError> {
+    let mut dst = Vec::new();
+    let mut rest = text;
+    while let Some(i) = rest.find('<') {
+        let j = match rest[i..].find('>') {
+            Some(j) => i + j,
+            None => return syntax_error("an unclosed tag"),
+        };
+        let tag = rest[i + 1..j].trim_end_matches('/');
+        rest = &rest[j + 1..];
+        if tag.starts_with('/') || tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+
+        let (name, mut attrs) = tag.split_at(tag.find(char::is_whitespace).unwrap_or(tag.len()));
+        let mut pairs = Vec::new();
+        while let Some(k) = attrs.find("=\"") {
+            let key = attrs[..k].trim();
+            let value = &attrs[k + 2..];
+            let end = match value.find('"') {
+                Some(e) => e,
+                None => return syntax_error(tag),
+            };
+            pairs.push((key, &value[..end]));
+            attrs = &value[end + 1..];
+        }
+        dst.push((name, pairs));
+    }
+    Ok(dst)
+}
+
+fn numbers(text: &str) -> Result<Vec<i64>, ReplayError> {
+    text.split(',')
+        .filter(|t| !t.is_empty())
+        .map(|t| t.trim().parse().or_else(|_| syntax_error(text)))
+        .collect()
+}
+
+// a call `m` of the player `who`.  `tile` is the last discard for chi, pon and open kans.
+fn parse_mjlog_meld(who: usize, m: i64) -> Result<Event, ReplayError> {
+    let from = (who + (m & 3) as usize) % N_PLAYERS;
+    // the kind of tiles of a chi, pon or added kan, and the base of a chi, which is a suited tile of 1 to 7.
+    let tile = |t: i64| match t {
+        0..=33 => Ok(t as usize),
+        _ => Err(ReplayError::InvalidMeld(m)),
+    };
+    if m & 0x4 != 0 {
+        let t = (m >> 10) / 3;
+        let base = tile(t / 7 * 9 + t % 7)?;
+        if base >= 27 || base % 9 >= 7 {
+            return Err(ReplayError::InvalidMeld(m));
+        }
+        Ok(Event::Call {
+            seat: who,
+            from,
+            meld: Meld::Chi(base),
+        })
+    } else if m & 0x8 != 0 {
+        Ok(Event::Call {
+            seat: who,
+            from,
+            meld: Meld::Pon(tile((m >> 9) / 3)?),
+        })
+    } else if m & 0x10 != 0 {
+        Ok(Event::AddedKan {
+            seat: who,
+            tile: tile((m >> 9) / 3)?,
+        })
+    } else if m & 0x20 != 0 {
+        syntax_error("nukidora of sanma")
+    } else {
+        let tile = parse_tenhou136_tile((m >> 8) & 0xff)?;
+        if from == who {
+            Ok(Event::ClosedKan { seat: who, tile })
+        } else {
+            Ok(Event::Call {
+                seat: who,
+                from,
+                meld: Meld::Kan(tile),
+            })
+        }
+    }
+}
+
+// the XML format of tenhou.net, i.e. the uncompressed mjlog.
+pub fn parse_mjlog(text: &str) -> Result<Replay, ReplayError> {
+    let mut replay = Replay {
+        names: Vec::new(),
+        rounds: Vec::new(),
+    };
+    let mut riichi = [false; N_PLAYERS];
+    let mut drawn = [None; N_PLAYERS];
+    for (name, attrs) in xml_tags(text)? {
+        let attr = |key: &str| attrs.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+        let number = |key: &str| -> Result<i64, ReplayError> {
+            attr(key)
+                .and_then(|v| v.parse().ok())
+                .map_or_else(|| syntax_error(name), Ok)
+        };
+
+        // draws are T, U, V and W, and discards are D, E, F and G followed by the tile.
+        let mut chars = name.chars();
+        let kind = chars.next().unwrap_or(' ');
+        if let Ok(id) = chars.as_str().parse::<i64>() {
+            let round = match replay.rounds.last_mut() {
+                Some(r) => r,
+                None => return syntax_error(name),
+            };
+            let tile = parse_tenhou136_tile(id)?;
+            if let Some(seat) = "TUVW".find(kind) {
+                drawn[seat] = Some(id);
+                round.events.push(Event::Draw { seat, tile });
+            } else if let Some(seat) = "DEFG".find(kind) {
+                round.events.push(Event::Discard {
+                    seat,
+                    tile,
+                    riichi: mem::replace(&mut riichi[seat], false),
+                    tsumogiri: drawn[seat] == Some(id),
+                });
+                drawn[seat] = None;
+            } else {
+                return syntax_error(name);
+            }
+            continue;
+        }
+
+        let round = replay.rounds.last_mut();
+        match (name, round) {
+            ("UN", _) => {
+                if let Some(n) = attr("n0") {
+                    replay.names = (0..N_PLAYERS)
+                        .map(|i| percent_decode(attr(&format!("n{}", i)).unwrap_or(n)))
+                        .collect();
+                }
+            }
+            ("INIT", _) => {
+                let seed = numbers(attr("seed").unwrap_or(""))?;
+                let ten = numbers(attr("ten").unwrap_or(""))?;
+                if seed.len() < 6 || ten.len() < N_PLAYERS {
+                    return syntax_error(name);
+                }
+                let mut hands = Vec::new();
+                for i in 0..N_PLAYERS {
+                    let mut hand = TileSet::new();
+                    for id in numbers(attr(&format!("hai{}", i)).unwrap_or(""))? {
+                        hand.add(parse_tenhou136_tile(id)?)?;
+                    }
+                    hands.push(hand);
+                }
+                let dealer = match number("oya")? {
+                    oya @ 0..=3 => oya as usize,
+                    _ => return syntax_error(name),
+                };
+                let mut scores = [0; N_PLAYERS];
+                for (s, t) in scores.iter_mut().zip(ten.iter()) {
+                    *s = 100 * *t as isize;
+                }
+                replay.rounds.push(Round {
+                    round_wind: seed[0] as usize / N_PLAYERS,
+                    dealer,
+                    honba: seed[1] as usize,
+                    deposits: seed[2] as usize,
+                    scores,
+                    dora_indicator: parse_tenhou136_tile(seed[5])?,
+                    hands,
+                    events: Vec::new(),
+                });
+                riichi = [false; N_PLAYERS];
+                drawn = [None; N_PLAYERS];
+            }
+            ("N", Some(r)) => {
+                let who = number("who")? as usize % N_PLAYERS;
+                r.events.push(parse_mjlog_meld(who, number("m")?)?);
+            }
+            ("REACH", Some(_)) if number("step")? == 1 => {
+                riichi[number("who")? as usize % N_PLAYERS] = true;
+            }
+            ("DORA", Some(r)) => r.events.push(Event::Dora(parse_tenhou136_tile(number("hai")?)?)),
+            ("AGARI", Some(r)) => r.events.push(Event::Win {
+                seat: number("who")? as usize % N_PLAYERS,
+                from: number("fromWho")? as usize % N_PLAYERS,
+            }),
+            ("RYUUKYOKU", Some(r)) => r.events.push(Event::Ryuukyoku),
+            _ => (),
+        }
+    }
+    Ok(replay)
+}
+
+// 11..19, 21..29 and 31..39 are suited tiles, 41..47 are honors and 51..53 are red fives.
+fn parse_tenhou6_tile(code: i64) -> Result<usize, ReplayError> {
+    match code {
+        11..=19 | 21..=29 | 31..=39 => Ok((code / 10 - 1) as usize * 9 + (code % 10 - 1) as usize),
+        41..=47 => Ok(27 + (code - 41) as usize),
+        51..=53 => Ok((code - 51) as usize * 9 + 4),
+        _ => Err(ReplayError::InvalidTile(code)),
+    }
+}
+
+// the tenhou.net/6 notation of a call, e.g. "c275226" or "4545p45": the letter, the tile after it and the player
+// it is called from, counted from the caller (1: shimocha, 2: toimen and 3: kamicha) by the letter's position.
+fn parse_tenhou6_call(text: &str) -> Result<(char, usize, usize), ReplayError> {
+    let i = match text.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(i) => i,
+        None => return syntax_error(text),
+    };
+    let letter = text[i..].chars().next().unwrap();
+    let code = text.get(i + 1..i + 3).and_then(|t| t.parse().ok());
+    let tile = parse_tenhou6_tile(code.map_or_else(|| syntax_error(text), Ok)?)?;
+    let from = match i {
+        0 => 3,
+        2 => 2,
+        _ => 1,
+    };
+    Ok((letter, tile, from))
+}
+
+// the JSON format of tenhou.net/6, where each round is the initial hand, the draws and the discards of each player.
+// the turns are reconstructed by checking each discard against the next draw of the other players.
+pub fn parse_tenhou6(text: &str) -> Result<Replay, ReplayError> {
+    let json: Value = serde_json::from_str(text).or_else(|e| syntax_error(&e.to_string()))?;
+    let names = match json["name"].as_array() {
+        Some(n) => n.iter().map(|e| e.as_str().unwrap_or("").to_string()).collect(),
+        None => Vec::new(),
+    };
+    let mut rounds = Vec::new();
+    for log in json["log"].as_array().map_or(&[][..], |v| &v[..]) {
+        rounds.push(parse_tenhou6_round(log)?);
+    }
+    Ok(Replay { names, rounds })
+}
+
+fn parse_tenhou6_round(log: &Value) -> Result<Round, ReplayError> {
+    let number = |v: &Value| v.as_i64().map_or_else(|| syntax_error(&v.to_string()), Ok);
+    let array = |v: &Value| v.as_array().cloned().map_or_else(|| syntax_error(&v.to_string()), Ok);
+    let kyoku = array(&log[0])?;
+    let mut scores = [0; N_PLAYERS];
+    for (s, v) in scores.iter_mut().zip(array(&log[1])?.iter()) {
+        *s = number(v)? as isize;
+    }
+    let mut doras = Vec::new();
+    for v in array(&log[2])? {
+        doras.push(parse_tenhou6_tile(number(&v)?)?);
+    }
+    let mut hands = Vec::new();
+    let mut draws = Vec::new();
+    let mut discards = Vec::new();
+    for i in 0..N_PLAYERS {
+        let mut hand = TileSet::new();
+        for v in array(&log[4 + 3 * i])? {
+            hand.add(parse_tenhou6_tile(number(&v)?)?)?;
+        }
+        hands.push(hand);
+        draws.push(array(&log[5 + 3 * i])?);
+        discards.push(array(&log[6 + 3 * i])?);
+    }
+    let dealer = number(&kyoku[0])? as usize % N_PLAYERS;
+    let mut round = Round {
+        round_wind: number(&kyoku[0])? as usize / N_PLAYERS,
+        dealer,
+        honba: number(&kyoku[1])? as usize,
+        deposits: number(&kyoku[2])? as usize,
+        scores,
+        dora_indicator: *doras.first().map_or_else(|| syntax_error("no dora indicator"), Ok)?,
+        hands,
+        events: Vec::new(),
+    };
+
+    let mut doras = doras.into_iter().skip(1);
+    let mut next_draw = [0; N_PLAYERS];
+    let mut next_discard = [0; N_PLAYERS];
+    let mut drawn = [None; N_PLAYERS];
+    let mut seat = dealer;
+    'turn: while let Some(draw) = draws[seat].get(next_draw[seat]) {
+        next_draw[seat] += 1;
+        match draw {
+            Value::String(text) => {
+                let (letter, tile, from) = parse_tenhou6_call(text)?;
+                let meld = match letter {
+                    'c' => {
+                        let mut tiles = vec![tile];
+                        for k in [3, 5].iter() {
+                            let code = text.get(*k..*k + 2).and_then(|t| t.parse().ok());
+                            tiles.push(parse_tenhou6_tile(code.map_or_else(|| syntax_error(text), Ok)?)?);
+                        }
+                        Meld::Chi(*tiles.iter().min().unwrap())
+                    }
+                    'p' => Meld::Pon(tile),
+                    'm' => Meld::Kan(tile),
+                    _ => return syntax_error(text),
+                };
+                round.events.push(Event::Call {
+                    seat,
+                    from: (seat + from) % N_PLAYERS,
+                    meld,
+                });
+                if letter == 'm' {
+                    // the discard slot of an open kan is a placeholder, followed by the draw from the dead wall.
+                    next_discard[seat] += 1;
+                    round.events.extend(doras.next().map(Event::Dora));
+                    continue;
+                }
+                drawn[seat] = None;
+            }
+            v => {
+                let tile = parse_tenhou6_tile(number(v)?)?;
+                drawn[seat] = Some(tile);
+                round.events.push(Event::Draw { seat, tile });
+            }
+        }
+
+        let discard = match discards[seat].get(next_discard[seat]) {
+            Some(d) => d,
+            None => break,
+        };
+        next_discard[seat] += 1;
+        let (tile, riichi) = match discard {
+            // "r60" is a riichi by tsumogiri.
+            Value::String(text) if text == "r60" => (drawn[seat], true),
+            Value::String(text) => {
+                let (letter, tile, _) = parse_tenhou6_call(text)?;
+                match letter {
+                    'a' | 'k' => {
+                        round.events.push(match letter {
+                            'a' => Event::ClosedKan { seat, tile },
+                            _ => Event::AddedKan { seat, tile },
+                        });
+                        round.events.extend(doras.next().map(Event::Dora));
+                        continue 'turn;
+                    }
+                    'r' => (Some(tile), true),
+                    _ => return syntax_error(text),
+                }
+            }
+            v => match number(v)? {
+                60 => (drawn[seat], false),
+                code => (Some(parse_tenhou6_tile(code)?), false),
+            },
+        };
+        let tile = tile.map_or_else(|| syntax_error("a tsumogiri without a draw"), Ok)?;
+        round.events.push(Event::Discard {
+            seat,
+            tile,
+            riichi,
+            tsumogiri: discard.as_i64() == Some(60) || discard.as_str() == Some("r60"),
+        });
+
+        // the next player is a caller of the discard, a pon or kan before a chi, or else shimocha.
+        let mut caller = None;
+        for i in 1..N_PLAYERS {
+            let s = (seat + i) % N_PLAYERS;
+            let text = match draws[s].get(next_draw[s]).and_then(|d| d.as_str()) {
+                Some(t) => t,
+                None => continue,
+            };
+            let (letter, t, from) = parse_tenhou6_call(text)?;
+            if t == tile && (s + from) % N_PLAYERS == seat && caller.is_none_or(|(l, _)| l == 'c') {
+                caller = Some((letter, s));
+            }
+        }
+        seat = caller.map_or((seat + 1) % N_PLAYERS, |(_, s)| s);
+    }
+
+    let result = array(&log[4 + 3 * N_PLAYERS])?;
+    if result.first().and_then(|r| r.as_str()) == Some("和了") {
+        for info in result.iter().skip(2).step_by(2) {
+            let info = array(info)?;
+            if info.len() < 2 {
+                return syntax_error(&result[0].to_string());
+            }
+            round.events.push(Event::Win {
+                seat: number(&info[0])? as usize,
+                from: number(&info[1])? as usize,
+            });
+        }
+    } else {
+        round.events.push(Event::Ryuukyoku);
+    }
+    Ok(round)
+}
@@ -0,0 +1,164 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::playout;
+use crate::replay::*;
+use crate::search;
+use crate::tile::*;
+use serde_json::{json, Value};
+use std::*;
+
+pub const DEFAULT_THRESHOLD: f64 = 0.1;
+const WINDS: [char; 4] = ['E', 'S', 'W', 'N'];
+
+// the engine grading the discards.  lower scores are better for both, in the units of the engine.
+#[derive(Clone, Copy, Debug)]
+pub enum Engine {
+    // the min-mean # of non-meld tiles, by `search::discard_tile_parallel()`.
+    Search { depth: usize },
+    // the mean # of draws to win, by `playout::discard_tile_parallel()`, seeded by `seed` at every discard.
+    Playout { depth: usize, n_samples: usize, seed: u64 },
+}
+
+impl Engine {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Engine::Search { .. } => "search",
+            Engine::Playout { .. } => "playout",
+        }
+    }
+
+    fn scores(&self, hand: &TileSet, wall: &TileSet, n_draws: usize) -> Vec<(usize, f64)> {
+        let (mut hand, mut wall) = (hand.clone(), wall.clone());
+        let (_, scores) = match *self {
+            Engine::Search { depth } => search::discard_tile_parallel(&mut hand, &mut wall, depth),
+            Engine::Playout { depth, n_samples, seed } => {
+                playout::discard_tile_parallel(&mut hand, &mut wall, n_draws, depth, n_samples, seed)
+            }
+        };
+        scores
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Grade {
+    // e.g. "E1-0" for the first round of east with no honba.
+    pub round: String,
+    // the # of the discard in the round, from 1.
+    pub turn: usize,
+    pub hand: TileSet,
+    pub melds: Vec<Meld>,
+    pub discard: usize,
+    pub best: usize,
+    // the score of each discard, from the best.
+    pub scores: Vec<(usize, f64)>,
+    // how much worse the discard is than the best.  NaN if the engine could not score the discard, which is flagged.
+    pub loss: f64,
+    pub flagged: bool,
+}
+
+fn round_name(round: &Round) -> String {
+    format!("{}{}-{}", WINDS[round.round_wind % 4], round.dealer + 1, round.honba)
+}
+
+// grades every discard of `seat` in `replay`, flagging the ones worse than the best by more than `threshold`.  the
+// discards after riichi and of complete hands are skipped, as there is no choice.
+pub fn review(replay: &Replay, seat: usize, engine: &Engine, threshold: f64) -> Result<Vec<Grade>, ReplayError> {
+    let mut dst = Vec::new();
+    for (i, round) in replay.rounds.iter().enumerate() {
+        let mut riichi = false;
+        for (turn, p) in round.positions(i, seat)?.into_iter().enumerate() {
+            if riichi {
+                break;
+            }
+            riichi = p.riichi;
+
+            let wall = p.table.wall(&p.hand)?;
+            let mut scores = engine.scores(&p.hand, &wall, p.table.n_draws(&wall));
+            if scores.is_empty() {
+                continue;
+            }
+            scores.sort_by(|(_, s0), (_, s1)| s0.total_cmp(s1));
+            let (best, best_score) = scores[0];
+            // every tile of the hand is scored, so the log does not follow the hand without the discard.
+            let score = match scores.iter().find(|(t, _)| *t == p.discard) {
+                Some((_, s)) => *s,
+                None => return Err(ReplayError::Tile(TileError::NotPresent(p.discard))),
+            };
+            // the discards as hopeless as the best, e.g. both infinite, lose nothing.
+            let loss = if score == best_score { 0.0 } else { score - best_score };
+            dst.push(Grade {
+                round: round_name(round),
+                turn: turn + 1,
+                hand: p.hand,
+                melds: p.melds,
+                discard: p.discard,
+                best,
+                scores,
+                loss,
+                flagged: loss > threshold || loss.is_nan(),
+            });
+        }
+    }
+    Ok(dst)
+}
+
+fn format_hand(hand: &TileSet) -> String {
+    format_tile_set(hand).trim_end().to_string()
+}
+
+fn format_melds(melds: &[Meld]) -> String {
+    melds
+        .iter()
+        .map(|m| format!(" [{}]", format_hand(&m.tiles())))
+        .collect()
+}
+
+pub fn format_text(grades: &[Grade]) -> String {
+    let mut dst = String::new();
+    for g in grades.iter() {
+        dst += &format!(
+            "{} #{:<2} {} {}{}\n",
+            g.round,
+            g.turn,
+            if g.flagged { "*" } else { " " },
+            format_hand(&g.hand),
+            format_melds(&g.melds),
+        );
+        dst += &format!(
+            "       discard {} ({:.4}), best {} ({:.4}), loss {:.4}\n",
+            format_tile(g.discard),
+            g.scores[0].1 + g.loss,
+            format_tile(g.best),
+            g.scores[0].1,
+            g.loss,
+        );
+    }
+    let n_flagged = grades.iter().filter(|g| g.flagged).count();
+    dst += &format!("{} of {} discards flagged.\n", n_flagged, grades.len());
+    dst
+}
+
+pub fn to_json(grades: &[Grade], seat: usize, engine: &Engine, threshold: f64) -> Value {
+    let decisions: Vec<_> = grades
+        .iter()
+        .map(|g| {
+            json!({
+                "round": g.round,
+                "turn": g.turn,
+                "hand": format_hand(&g.hand),
+                "melds": g.melds.iter().map(|m| format_hand(&m.tiles())).collect::<Vec<_>>(),
+                "discard": format_tile(g.discard),
+                "best": format_tile(g.best),
+                "scores": g.scores.iter().map(|(t, s)| json!([format_tile(*t), s])).collect::<Vec<_>>(),
+                "loss": g.loss,
+                "flagged": g.flagged,
+            })
+        })
+        .collect();
+    json!({
+        "seat": seat,
+        "engine": engine.name(),
+        "threshold": threshold,
+        "n_flagged": grades.iter().filter(|g| g.flagged).count(),
+        "decisions": decisions,
+    })
+}
@@ -0,0 +1,587 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::decompose;
+pub use crate::decompose::Wait;
+use crate::tile::*;
+use std::*;
+
+const HAKU: usize = 31;
+const HATSU: usize = 32;
+const CHUN: usize = 33;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WinType {
+    Tsumo,
+    Ron,
+}
+
+#[derive(Clone, Debug)]
+pub struct WinContext {
+    pub win_type: WinType,
+    // 0 = east, ..., 3 = north.  the dealer sits east.
+    pub seat_wind: usize,
+    pub round_wind: usize,
+    pub dora_indicators: Vec<usize>,
+    pub ura_indicators: Vec<usize>,
+    pub n_red: usize,
+    pub riichi: bool,
+    pub double_riichi: bool,
+    pub ippatsu: bool,
+    pub last_tile: bool,
+    pub rinshan: bool,
+    pub chankan: bool,
+    pub first_turn: bool,
+    pub honba: usize,
+}
+
+impl WinContext {
+    pub fn new(win_type: WinType) -> Self {
+        WinContext {
+            win_type,
+            seat_wind: 0,
+            round_wind: 0,
+            dora_indicators: Vec::new(),
+            ura_indicators: Vec::new(),
+            n_red: 0,
+            riichi: false,
+            double_riichi: false,
+            ippatsu: false,
+            last_tile: false,
+            rinshan: false,
+            chankan: false,
+            first_turn: false,
+            honba: 0,
+        }
+    }
+
+    pub fn is_dealer(&self) -> bool {
+        self.seat_wind == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Yaku {
+    Riichi,
+    DoubleRiichi,
+    Ippatsu,
+    MenzenTsumo,
+    Pinfu,
+    Tanyao,
+    Iipeikou,
+    Haku,
+    Hatsu,
+    Chun,
+    SeatWind,
+    RoundWind,
+    Haitei,
+    Houtei,
+    Rinshan,
+    Chankan,
+    Chiitoitsu,
+    Sanshoku,
+    Ittsu,
+    Chanta,
+    Toitoi,
+    Sanankou,
+    Sankantsu,
+    SanshokuDoukou,
+    Shousangen,
+    Honroutou,
+    Honitsu,
+    Junchan,
+    Ryanpeikou,
+    Chinitsu,
+    Kokushi,
+    Suuankou,
+    Daisangen,
+    Shousuushii,
+    Daisuushii,
+    Tsuuiisou,
+    Chinroutou,
+    Ryuuiisou,
+    Chuuren,
+    Suukantsu,
+    Tenhou,
+    Chiihou,
+}
+
+impl Yaku {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Yaku::Riichi => "riichi",
+            Yaku::DoubleRiichi => "double riichi",
+            Yaku::Ippatsu => "ippatsu",
+            Yaku::MenzenTsumo => "menzen tsumo",
+            Yaku::Pinfu => "pinfu",
+            Yaku::Tanyao => "tanyao",
+            Yaku::Iipeikou => "iipeikou",
+            Yaku::Haku => "haku",
+            Yaku::Hatsu => "hatsu",
+            Yaku::Chun => "chun",
+            Yaku::SeatWind => "seat wind",
+            Yaku::RoundWind => "round wind",
+            Yaku::Haitei => "haitei",
+            Yaku::Houtei => "houtei",
+            Yaku::Rinshan => "rinshan kaihou",
+            Yaku::Chankan => "chankan",
+            Yaku::Chiitoitsu => "chiitoitsu",
+            Yaku::Sanshoku => "sanshoku doujun",
+            Yaku::Ittsu => "ittsu",
+            Yaku::Chanta => "chanta",
+            Yaku::Toitoi => "toitoi",
+            Yaku::Sanankou => "sanankou",
+            Yaku::Sankantsu => "sankantsu",
+            Yaku::SanshokuDoukou => "sanshoku doukou",
+            Yaku::Shousangen => "shousangen",
+            Yaku::Honroutou => "honroutou",
+            Yaku::Honitsu => "honitsu",
+            Yaku::Junchan => "junchan",
+            Yaku::Ryanpeikou => "ryanpeikou",
+            Yaku::Chinitsu => "chinitsu",
+            Yaku::Kokushi => "kokushi musou",
+            Yaku::Suuankou => "suuankou",
+            Yaku::Daisangen => "daisangen",
+            Yaku::Shousuushii => "shousuushii",
+            Yaku::Daisuushii => "daisuushii",
+            Yaku::Tsuuiisou => "tsuuiisou",
+            Yaku::Chinroutou => "chinroutou",
+            Yaku::Ryuuiisou => "ryuuiisou",
+            Yaku::Chuuren => "chuuren poutou",
+            Yaku::Suukantsu => "suukantsu",
+            Yaku::Tenhou => "tenhou",
+            Yaku::Chiihou => "chiihou",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Payment {
+    Ron(usize),
+    // the dealer wins: every other player pays the same.
+    TsumoAll(usize),
+    // (each non-dealer pays, the dealer pays).
+    Tsumo(usize, usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Score {
+    // (yaku, han); a yakuman counts as 13 han.
+    pub yaku: Vec<(Yaku, usize)>,
+    pub dora: usize,
+    pub han: usize,
+    pub fu: usize,
+    pub yakuman: usize,
+    pub points: usize,
+    pub payment: Payment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Block {
+    Pair(usize),
+    Sequence(usize, bool),
+    Triplet(usize, bool),
+    Kan(usize, bool),
+}
+
+impl Block {
+    fn has_terminal_or_honor(&self) -> bool {
+        match *self {
+            Block::Sequence(i, _) => i % 9 == 0 || i % 9 == 6,
+            Block::Pair(i) | Block::Triplet(i, _) | Block::Kan(i, _) => is_terminal_or_honor(i),
+        }
+    }
+}
+
+pub fn is_honor(tile: usize) -> bool {
+    tile >= 27
+}
+
+pub fn is_terminal_or_honor(tile: usize) -> bool {
+    tile >= 27 || tile.is_multiple_of(9) || tile % 9 == 8
+}
+
+pub fn dora_from_indicator(tile: usize) -> usize {
+    match tile {
+        0..=26 => tile / 9 * 9 + (tile % 9 + 1) % 9,
+        27..=30 => 27 + (tile - 27 + 1) % 4,
+        31..=33 => 31 + (tile - 31 + 1) % 3,
+        _ => panic!(),
+    }
+}
+
+// `hand` is the concealed part including the winning tile.  returns `None` if the hand is not complete or has no yaku.
+pub fn score_hand(hand: &TileSet, melds: &[Meld], win_tile: usize, ctx: &WinContext) -> Option<Score> {
+    if hand.tile(win_tile) == 0 || hand.count() + 3 * melds.len() != 14 {
+        return None;
+    }
+    let mut all = hand.clone();
+    for m in melds {
+        all = all.union(&m.tiles()).ok()?;
+    }
+    let is_menzen = melds.iter().all(|m| !m.is_open());
+
+    let mut best: Option<Score> = None;
+    let mut update = |yaku: Vec<(Yaku, usize)>, fu: usize| {
+        if let Some(score) = finish(yaku, fu, &all, ctx) {
+            if best
+                .as_ref()
+                .is_none_or(|b| (score.points, score.han) > (b.points, b.han))
+            {
+                best = Some(score);
+            }
+        }
+    };
+
+    if melds.is_empty() && is_kokushi(hand) {
+        update(vec![(Yaku::Kokushi, 13)], 0);
+    }
+    if melds.is_empty() && is_chiitoitsu(hand) {
+        let mut yaku = common_yaku(&all, is_menzen, ctx);
+        yaku.push((Yaku::Chiitoitsu, 2));
+        update(yaku, 25);
+    }
+
+    for decomposition in decompose::complete_decompositions(hand) {
+        for (w, block) in decomposition.iter().enumerate() {
+            let wait = match decompose::wait_shape(block, win_tile) {
+                Some(w) => w,
+                None => continue,
+            };
+            let mut blocks: Vec<_> = decomposition
+                .iter()
+                .map(|b| match *b {
+                    decompose::Block::Pair(i) => Block::Pair(i),
+                    decompose::Block::Sequence(i) => Block::Sequence(i, true),
+                    decompose::Block::Triplet(i) => Block::Triplet(i, true),
+                    _ => unreachable!(),
+                })
+                .collect();
+            if let (Wait::Shanpon, WinType::Ron) = (wait, ctx.win_type) {
+                blocks[w] = Block::Triplet(win_tile, false);
+            }
+            for m in melds {
+                blocks.push(match *m {
+                    Meld::Chi(i) => Block::Sequence(i, false),
+                    Meld::Pon(i) => Block::Triplet(i, false),
+                    Meld::Kan(i) => Block::Kan(i, false),
+                    Meld::ClosedKan(i) => Block::Kan(i, true),
+                });
+            }
+            let (yaku, fu) = regular_yaku(&blocks, wait, &all, is_menzen, ctx);
+            update(yaku, fu);
+        }
+    }
+
+    best
+}
+
+pub fn is_chiitoitsu(hand: &TileSet) -> bool {
+    hand.count() == 14 && hand.iter().all(|e| *e == 0 || *e == 2)
+}
+
+pub fn is_kokushi(hand: &TileSet) -> bool {
+    if hand.count() != 14 {
+        return false;
+    }
+    let mut n_kinds = 0;
+    for (i, n) in hand.iter().enumerate() {
+        if *n > 0 && !is_terminal_or_honor(i) {
+            return false;
+        }
+        n_kinds += (*n > 0) as usize;
+    }
+    n_kinds == 13
+}
+
+// yaku which depend only on the situation and the set of tiles.
+fn common_yaku(all: &TileSet, is_menzen: bool, ctx: &WinContext) -> Vec<(Yaku, usize)> {
+    let mut yaku = Vec::new();
+    let open_bonus = |han| if is_menzen { han } else { han - 1 };
+
+    if ctx.double_riichi {
+        yaku.push((Yaku::DoubleRiichi, 2));
+    } else if ctx.riichi {
+        yaku.push((Yaku::Riichi, 1));
+    }
+    if ctx.ippatsu && (ctx.riichi || ctx.double_riichi) {
+        yaku.push((Yaku::Ippatsu, 1));
+    }
+    if is_menzen && ctx.win_type == WinType::Tsumo {
+        yaku.push((Yaku::MenzenTsumo, 1));
+    }
+    if ctx.last_tile && !ctx.rinshan {
+        match ctx.win_type {
+            WinType::Tsumo => yaku.push((Yaku::Haitei, 1)),
+            WinType::Ron => yaku.push((Yaku::Houtei, 1)),
+        }
+    }
+    if ctx.rinshan {
+        yaku.push((Yaku::Rinshan, 1));
+    }
+    if ctx.chankan {
+        yaku.push((Yaku::Chankan, 1));
+    }
+
+    let has = |f: &dyn Fn(usize) -> bool| all.iter().enumerate().any(|(i, n)| *n > 0 && f(i));
+    if !has(&is_terminal_or_honor) {
+        yaku.push((Yaku::Tanyao, 1));
+    }
+    if !has(&|i| !is_terminal_or_honor(i)) && has(&is_honor) && has(&|i| !is_honor(i)) {
+        yaku.push((Yaku::Honroutou, 2));
+    }
+    let n_suits = (0..3).filter(|t| has(&|i| !is_honor(i) && i / 9 == *t)).count();
+    if n_suits == 1 {
+        if has(&is_honor) {
+            yaku.push((Yaku::Honitsu, open_bonus(3)));
+        } else {
+            yaku.push((Yaku::Chinitsu, open_bonus(6)));
+        }
+    }
+    yaku
+}
+
+fn regular_yaku(
+    blocks: &[Block],
+    wait: Wait,
+    all: &TileSet,
+    is_menzen: bool,
+    ctx: &WinContext,
+) -> (Vec<(Yaku, usize)>, usize) {
+    let mut yaku = common_yaku(all, is_menzen, ctx);
+    let open_bonus = |han| if is_menzen { han } else { han - 1 };
+
+    let pair = blocks
+        .iter()
+        .find_map(|b| match *b {
+            Block::Pair(i) => Some(i),
+            _ => None,
+        })
+        .unwrap();
+    let mut sequences = Vec::new();
+    let mut triplets = Vec::new();
+    let mut n_concealed = 0;
+    let mut n_kans = 0;
+    for b in blocks {
+        match *b {
+            Block::Pair(_) => (),
+            Block::Sequence(i, _) => sequences.push(i),
+            Block::Triplet(i, _) | Block::Kan(i, _) => triplets.push(i),
+        }
+        if let Block::Triplet(_, true) | Block::Kan(_, true) = *b {
+            n_concealed += 1;
+        }
+        if let Block::Kan(..) = *b {
+            n_kans += 1;
+        }
+    }
+    let is_value = |i: usize| i >= HAKU || i == 27 + ctx.seat_wind || i == 27 + ctx.round_wind;
+
+    let is_pinfu = is_menzen && sequences.len() == 4 && !is_value(pair) && wait == Wait::Ryanmen;
+    if is_pinfu {
+        yaku.push((Yaku::Pinfu, 1));
+    }
+
+    if is_menzen {
+        let mut n_peikou = 0;
+        let mut seqs = sequences.clone();
+        seqs.sort_unstable();
+        let mut k = 0;
+        while k + 1 < seqs.len() {
+            if seqs[k] == seqs[k + 1] {
+                n_peikou += 1;
+                k += 2;
+            } else {
+                k += 1;
+            }
+        }
+        match n_peikou {
+            1 => yaku.push((Yaku::Iipeikou, 1)),
+            2 => yaku.push((Yaku::Ryanpeikou, 3)),
+            _ => (),
+        }
+    }
+
+    for (tile, y) in [(HAKU, Yaku::Haku), (HATSU, Yaku::Hatsu), (CHUN, Yaku::Chun)].iter() {
+        if triplets.contains(tile) {
+            yaku.push((*y, 1));
+        }
+    }
+    if triplets.contains(&(27 + ctx.seat_wind)) {
+        yaku.push((Yaku::SeatWind, 1));
+    }
+    if triplets.contains(&(27 + ctx.round_wind)) {
+        yaku.push((Yaku::RoundWind, 1));
+    }
+
+    if (0..7).any(|i| (0..3).all(|t| sequences.contains(&(9 * t + i)))) {
+        yaku.push((Yaku::Sanshoku, open_bonus(2)));
+    }
+    if (0..3).any(|t| [0, 3, 6].iter().all(|i| sequences.contains(&(9 * t + i)))) {
+        yaku.push((Yaku::Ittsu, open_bonus(2)));
+    }
+    if !sequences.is_empty() && blocks.iter().all(|b| b.has_terminal_or_honor()) {
+        if blocks
+            .iter()
+            .any(|b| matches!(*b, Block::Pair(i) | Block::Triplet(i, _) | Block::Kan(i, _) if is_honor(i)))
+        {
+            yaku.push((Yaku::Chanta, open_bonus(2)));
+        } else {
+            yaku.push((Yaku::Junchan, open_bonus(3)));
+        }
+    }
+    if triplets.len() == 4 {
+        yaku.push((Yaku::Toitoi, 2));
+    }
+    if n_concealed == 3 {
+        yaku.push((Yaku::Sanankou, 2));
+    }
+    if n_kans == 3 {
+        yaku.push((Yaku::Sankantsu, 2));
+    }
+    if (0..9).any(|i| (0..3).all(|t| triplets.contains(&(9 * t + i)))) {
+        yaku.push((Yaku::SanshokuDoukou, 2));
+    }
+    let n_dragons = triplets.iter().filter(|i| **i >= HAKU).count();
+    if n_dragons == 2 && pair >= HAKU {
+        yaku.push((Yaku::Shousangen, 2));
+    }
+
+    // yakuman.
+    let n_winds = triplets.iter().filter(|i| (27..HAKU).contains(*i)).count();
+    if n_concealed == 4 {
+        yaku.push((Yaku::Suuankou, 13));
+    }
+    if n_dragons == 3 {
+        yaku.push((Yaku::Daisangen, 13));
+    }
+    if n_winds == 3 && (27..HAKU).contains(&pair) {
+        yaku.push((Yaku::Shousuushii, 13));
+    }
+    if n_winds == 4 {
+        yaku.push((Yaku::Daisuushii, 13));
+    }
+    if n_kans == 4 {
+        yaku.push((Yaku::Suukantsu, 13));
+    }
+    if is_menzen && n_kans == 0 && is_chuuren(all) {
+        yaku.push((Yaku::Chuuren, 13));
+    }
+
+    let mut fu: usize = 20;
+    if !(is_pinfu && ctx.win_type == WinType::Tsumo) {
+        if is_menzen && ctx.win_type == WinType::Ron {
+            fu += 10;
+        }
+        if ctx.win_type == WinType::Tsumo {
+            fu += 2;
+        }
+        for b in blocks {
+            let (i, c, k) = match *b {
+                Block::Triplet(i, c) => (i, c, 1),
+                Block::Kan(i, c) => (i, c, 4),
+                _ => continue,
+            };
+            let base = if is_terminal_or_honor(i) { 4 } else { 2 };
+            fu += base * if c { 2 } else { 1 } * k;
+        }
+        if pair >= HAKU {
+            fu += 2;
+        }
+        if pair == 27 + ctx.seat_wind {
+            fu += 2;
+        }
+        if pair == 27 + ctx.round_wind {
+            fu += 2;
+        }
+        if let Wait::Kanchan | Wait::Penchan | Wait::Tanki = wait {
+            fu += 2;
+        }
+        fu = cmp::max(fu.div_ceil(10) * 10, 30);
+    }
+
+    (yaku, fu)
+}
+
+fn is_chuuren(all: &TileSet) -> bool {
+    (0..3).any(|t| {
+        all.iter().enumerate().all(|(i, n)| *n == 0 || i / 9 == t)
+            && (0..9).all(|i| all.simple(t, i) >= if i == 0 || i == 8 { 3 } else { 1 })
+    })
+}
+
+fn finish(mut yaku: Vec<(Yaku, usize)>, fu: usize, all: &TileSet, ctx: &WinContext) -> Option<Score> {
+    let only = |f: &dyn Fn(usize) -> bool| all.iter().enumerate().all(|(i, n)| *n == 0 || f(i));
+    if only(&is_honor) {
+        yaku.push((Yaku::Tsuuiisou, 13));
+    }
+    if only(&|i| is_terminal_or_honor(i) && !is_honor(i)) {
+        yaku.push((Yaku::Chinroutou, 13));
+    }
+    if only(&|i| [19, 20, 21, 23, 25, HATSU].contains(&i)) {
+        yaku.push((Yaku::Ryuuiisou, 13));
+    }
+    if ctx.first_turn && ctx.win_type == WinType::Tsumo {
+        yaku.push((if ctx.is_dealer() { Yaku::Tenhou } else { Yaku::Chiihou }, 13));
+    }
+
+    let yakuman = yaku.iter().filter(|(_, h)| *h >= 13).count();
+    if yakuman > 0 {
+        yaku.retain(|(_, h)| *h >= 13);
+    }
+    if yaku.is_empty() {
+        return None;
+    }
+
+    let mut dora = 0;
+    if yakuman == 0 {
+        for i in ctx.dora_indicators.iter() {
+            dora += all.tile(dora_from_indicator(*i)) as usize;
+        }
+        if ctx.riichi || ctx.double_riichi {
+            for i in ctx.ura_indicators.iter() {
+                dora += all.tile(dora_from_indicator(*i)) as usize;
+            }
+        }
+        dora += ctx.n_red;
+    }
+    let han = yaku.iter().map(|(_, h)| h).sum::<usize>() + dora;
+
+    let base = if yakuman > 0 {
+        8000 * yakuman
+    } else if han >= 13 {
+        8000
+    } else if han >= 11 {
+        6000
+    } else if han >= 8 {
+        4000
+    } else if han >= 6 {
+        3000
+    } else {
+        cmp::min(fu << (han + 2), 2000)
+    };
+    let round_up = |x: usize| x.div_ceil(100) * 100;
+    let (points, payment) = match ctx.win_type {
+        WinType::Ron => {
+            let p = round_up(base * if ctx.is_dealer() { 6 } else { 4 }) + 300 * ctx.honba;
+            (p, Payment::Ron(p))
+        }
+        WinType::Tsumo if ctx.is_dealer() => {
+            let p = round_up(2 * base) + 100 * ctx.honba;
+            (3 * p, Payment::TsumoAll(p))
+        }
+        WinType::Tsumo => {
+            let p0 = round_up(base) + 100 * ctx.honba;
+            let p1 = round_up(2 * base) + 100 * ctx.honba;
+            (2 * p0 + p1, Payment::Tsumo(p0, p1))
+        }
+    };
+
+    Some(Score {
+        yaku,
+        dora,
+        han,
+        fu,
+        yakuman,
+        points,
+        payment,
+    })
+}
@@ -0,0 +1,370 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::bench;
+use crate::shanten;
+use crate::table::*;
+use crate::tile::*;
+use rand::SeedableRng;
+use serde_json::{json, Value};
+use std::io::{BufRead, Read, Write};
+use std::*;
+
+const MAX_HEADER: usize = 8 << 10;
+const MAX_BODY: usize = 64 << 10;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    // # of the threads serving the connections, which is also the most analyses running at once.  the analyses run
+    // on the rayon pool.
+    pub n_workers: usize,
+    // the time to wait for an analysis, and for the request and the response on the socket.
+    pub time_limit: time::Duration,
+    // the largest parameters a request may ask for.
+    pub max_depth: usize,
+    pub max_samples: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            n_workers: 4,
+            time_limit: time::Duration::from_secs(30),
+            max_depth: 3,
+            max_samples: 1 << 21,
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+// an error response, with the status code.
+struct Error(u16, String);
+
+impl<T: fmt::Display> From<T> for Error {
+    fn from(e: T) -> Self {
+        Error(400, e.to_string())
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+// decodes "%xx" and "+" of a query string.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut dst = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                dst.push(b);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => dst.push(b' '),
+            (b, _) => dst.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&dst).into_owned()
+}
+
+fn read_request(reader: &mut impl BufRead) -> Result<Request, Error> {
+    let mut head = String::new();
+    let mut n_bytes = 0;
+    let mut lines = Vec::new();
+    loop {
+        head.clear();
+        // a line is read up to the limit, which it may not reach without a newline.
+        let limit = (MAX_HEADER + 1 - n_bytes) as u64;
+        n_bytes += reader.by_ref().take(limit).read_line(&mut head)?;
+        if n_bytes > MAX_HEADER {
+            return Err(Error(413, "Too large header".to_string()));
+        }
+        let line = head.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line.to_string());
+    }
+    let mut words = lines.first().map_or("", |l| l.as_str()).split_whitespace();
+    let (method, target) = match (words.next(), words.next()) {
+        (Some(m), Some(t)) => (m.to_string(), t),
+        _ => return Err(Error(400, "Invalid request line".to_string())),
+    };
+    let (path, query) = match target.find('?') {
+        Some(i) => (&target[..i], &target[i + 1..]),
+        None => (target, ""),
+    };
+    let query = query
+        .split('&')
+        .filter(|q| !q.is_empty())
+        .map(|q| match q.find('=') {
+            Some(i) => (decode(&q[..i]), decode(&q[i + 1..])),
+            None => (decode(q), String::new()),
+        })
+        .collect();
+
+    let length = lines[1..]
+        .iter()
+        .filter_map(|l| l.split_once(':'))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("content-length"))
+        .map_or(Ok(0), |(_, v)| v.trim().parse::<usize>())?;
+    if length > MAX_BODY {
+        return Err(Error(413, "Too large body".to_string()));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query,
+        body,
+    })
+}
+
+fn parse_tiles(text: &str) -> Result<TileSet, Error> {
+    parse_tile_set(text).map_err(|e| Error(400, format!("Syntax error at {}: {} in {}", e.pos, e.kind, text)))
+}
+
+fn parse_wind(text: &str) -> Result<usize, Error> {
+    ["E", "S", "W", "N"]
+        .iter()
+        .position(|w| *w == text)
+        .ok_or_else(|| Error(400, format!("Invalid wind: {}", text)))
+}
+
+// the hand and the table, from the fields named after the command line options, e.g. {"hand": "123m456p789s11223z",
+// "discards": "1m9p", "riichi": [2]}.  `get` gives the value of each field.
+fn parse_position(get: impl Fn(&str) -> Option<Value>) -> Result<(TileSet, TableState), Error> {
+    let text = |key: &str| -> Result<Option<String>, Error> {
+        match get(key) {
+            Some(Value::String(s)) => Ok(Some(s)),
+            Some(Value::Null) | None => Ok(None),
+            Some(v) => Err(Error(400, format!("Invalid {}: {}", key, v))),
+        }
+    };
+    let number = |key: &str| -> Result<Option<usize>, Error> {
+        match get(key) {
+            Some(Value::Number(n)) => n.as_u64().map(|n| Some(n as usize)),
+            Some(Value::String(s)) => s.parse().ok().map(Some),
+            Some(Value::Null) | None => Some(None),
+            _ => None,
+        }
+        .ok_or_else(|| Error(400, format!("Invalid {}", key)))
+    };
+
+    let hand = parse_tiles(&text("hand")?.ok_or_else(|| Error(400, "Missing hand".to_string()))?)?;
+    let mut table = TableState::new();
+    let [shimocha, toimen, kamicha] = &mut table.opponents;
+    let fields = [
+        ("own_discards", &mut table.own_discards),
+        ("discards", &mut table.discards),
+        ("shimocha", &mut shimocha.discards),
+        ("toimen", &mut toimen.discards),
+        ("kamicha", &mut kamicha.discards),
+        ("melds", &mut table.melds),
+        ("dora", &mut table.dora_indicators),
+    ];
+    for (key, field) in fields {
+        if let Some(t) = text(key)? {
+            *field = parse_tiles(&t)?;
+        }
+    }
+    // the opponents in riichi, by 1 (shimocha), 2 (toimen) and 3 (kamicha), as in `--riichi`.
+    let riichi = match get("riichi") {
+        Some(Value::Array(a)) => a.iter().map(|v| v.as_u64().unwrap_or(0).to_string()).collect(),
+        Some(Value::String(s)) => s,
+        Some(Value::Number(n)) => n.to_string(),
+        _ => String::new(),
+    };
+    for c in riichi.chars() {
+        match c.to_digit(10) {
+            Some(i @ 1..=3) => table.opponents[i as usize - 1].riichi = true,
+            _ => return Err(Error(400, format!("Invalid riichi: {}", riichi))),
+        }
+    }
+    if let Some(w) = text("seat")? {
+        table.seat_wind = parse_wind(&w)?;
+    }
+    if let Some(w) = text("round")? {
+        table.round_wind = parse_wind(&w)?;
+    }
+    if let Some(n) = number("opponent_tiles")? {
+        table.opponent_tiles = n;
+    }
+    if let Some(n) = number("dead_wall")? {
+        table.dead_wall = n;
+    }
+    Ok((hand, table))
+}
+
+// an analysis over the time limit, which its worker waits for after the response.
+type Pending = sync::mpsc::Receiver<thread::Result<Result<bench::Analysis, String>>>;
+
+fn analyze(body: &[u8], config: &Config, pending: &mut Option<Pending>) -> Result<Value, Error> {
+    let request: Value = serde_json::from_slice(body)?;
+    if !request.is_object() {
+        return Err(Error(400, "Not an object".to_string()));
+    }
+    let (mut hand, table) = parse_position(|k| request.get(k).cloned())?;
+
+    let mut params = bench::Params::default();
+    let number = |key: &str, default: usize, max: usize| match request.get(key) {
+        None | Some(Value::Null) => Ok(cmp::min(default, max)),
+        Some(v) => match v.as_u64() {
+            Some(n) if n as usize <= max => Ok(n as usize),
+            _ => Err(Error(400, format!("Invalid {} (at most {})", key, max))),
+        },
+    };
+    params.depth = number("depth", params.depth, config.max_depth)?;
+    params.playout_depth = number("playout_depth", params.playout_depth, config.max_depth)?;
+    params.n_samples = number("n_samples", params.n_samples, config.max_samples)?;
+    params.engine = match request.get("engine") {
+        None | Some(Value::Null) => None,
+        Some(Value::String(e)) if ["search", "playout", "uct"].contains(&e.as_str()) => Some(e.clone()),
+        Some(e) => return Err(Error(400, format!("Invalid engine: {}", e))),
+    };
+    let mut rng = match request.get("seed").and_then(|s| s.as_u64()) {
+        Some(s) => rand::rngs::StdRng::seed_from_u64(s),
+        None => rand::rngs::StdRng::from_entropy(),
+    };
+
+    // the engines cannot be stopped: an analysis over the time limit runs to the end and is dropped, keeping its
+    // worker busy.
+    let deadline = time::Instant::now() + config.time_limit;
+    let (sender, receiver) = sync::mpsc::channel();
+    rayon::spawn(move || {
+        if time::Instant::now() < deadline {
+            // a panic of an engine fails the request, not the server.
+            let a = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                bench::analyze_hand(&mut hand, &table, &params, bench::Format::Json, &mut rng)
+            }));
+            let _ = sender.send(a);
+        }
+    });
+    match receiver.recv_timeout(config.time_limit) {
+        Ok(Ok(Ok(a))) => Ok(bench::to_json(&a)),
+        Ok(Ok(Err(e))) => Err(Error(400, e)),
+        Ok(Err(_)) => Err(Error(500, "Analysis failed".to_string())),
+        Err(_) => {
+            *pending = Some(receiver);
+            Err(Error(503, "Time limit exceeded".to_string()))
+        }
+    }
+}
+
+// the shanten number and ukeire of the hand, or of each discard.
+fn shanten(query: &[(String, String)]) -> Result<Value, Error> {
+    let get = |k: &str| query.iter().find(|(q, _)| q == k).map(|(_, v)| json!(v));
+    let (hand, table) = parse_position(get)?;
+    if hand.count().is_multiple_of(3) {
+        return Err(Error(400, "# of tiles must be 3n + 1 or 3n + 2".to_string()));
+    }
+    let wall = table
+        .wall(&hand)
+        .map_err(|e| Error(400, format!("Invalid hand: {}", e)))?;
+    let ukeire = |hand: &TileSet| {
+        let tiles = shanten::ukeire(hand, &wall);
+        json!({
+            "shanten": shanten::shanten(hand),
+            "ukeire": tiles.iter().map(|(_, n)| n).sum::<usize>(),
+            "tiles": tiles.iter().map(|(t, _)| format_tile(*t)).collect::<Vec<_>>(),
+        })
+    };
+    let mut dst = ukeire(&hand);
+    dst["hand"] = json!(format_tile_set(&hand).trim_end());
+    if hand.count() % 3 == 2 {
+        let mut discards = Vec::new();
+        let mut hand = hand.clone();
+        for i in 0..hand.len() {
+            if hand.tile(i) > 0 {
+                *hand.tile_mut(i) -= 1;
+                let mut d = ukeire(&hand);
+                d["tile"] = json!(format_tile(i));
+                discards.push(d);
+                *hand.tile_mut(i) += 1;
+            }
+        }
+        dst["discards"] = json!(discards);
+    }
+    Ok(dst)
+}
+
+fn respond(request: &Request, config: &Config, pending: &mut Option<Pending>) -> Result<Value, Error> {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/analyze") => analyze(&request.body, config, pending),
+        ("GET", "/shanten") => shanten(&request.query),
+        (_, "/analyze") | (_, "/shanten") => Err(Error(405, format!("{} not allowed", request.method))),
+        (_, path) => Err(Error(404, format!("No such path: {}", path))),
+    }
+}
+
+fn handle(stream: net::TcpStream, config: &Config) -> io::Result<()> {
+    stream.set_read_timeout(Some(config.time_limit))?;
+    stream.set_write_timeout(Some(config.time_limit))?;
+    let mut reader = io::BufReader::new(stream.try_clone()?);
+    let mut pending = None;
+    let result = read_request(&mut reader).and_then(|r| respond(&r, config, &mut pending));
+    let (status, body) = match result {
+        Ok(v) => (200, v),
+        Err(Error(status, e)) => (status, json!({ "error": e })),
+    };
+    let body = body.to_string();
+    let mut writer = io::BufWriter::new(stream);
+    let result = write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    )
+    .and_then(|_| writer.flush());
+    // closes the connection before waiting for the analysis.
+    drop((reader, writer));
+    if let Some(p) = pending {
+        let _ = p.recv();
+    }
+    result
+}
+
+// serves the connections of `listener` on a pool of `config.n_workers` threads, one request for each connection:
+//   POST /analyze   a json object of the hand, the table, "engine", "depth", "playout_depth", "n_samples" and "seed",
+//                   answered by `bench::to_json()`.
+//   GET /shanten    the hand and the table as a query, e.g. "/shanten?hand=123m456p789s11223z&discards=1z".
+pub fn serve(listener: net::TcpListener, config: &Config) -> io::Result<()> {
+    let (sender, receiver) = sync::mpsc::channel::<net::TcpStream>();
+    let receiver = sync::Arc::new(sync::Mutex::new(receiver));
+    for _ in 0..cmp::max(config.n_workers, 1) {
+        let receiver = receiver.clone();
+        let config = config.clone();
+        thread::spawn(move || loop {
+            let stream = match receiver.lock().unwrap().recv() {
+                Ok(s) => s,
+                Err(_) => return,
+            };
+            // a broken connection only ends itself.
+            let _ = handle(stream, &config);
+        });
+    }
+    for stream in listener.incoming().flatten() {
+        // the workers live as long as the receiver.
+        sender.send(stream).unwrap();
+    }
+    Ok(())
+}
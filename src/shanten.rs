@@ -0,0 +1,109 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::score::*;
+use crate::tile::*;
+use std::*;
+
+// -1 for a complete hand.  chiitoitsu and kokushi are considered for 13 or 14 tile hands.
+pub fn shanten(hand: &TileSet) -> isize {
+    let n_triads = hand.count() / 3;
+    let mut best = 0;
+    search(&mut hand.clone(), 0, n_triads, (0, 0, false), &mut best);
+    let mut n = 2 * n_triads as isize - best as isize;
+
+    if hand.count() >= 13 {
+        n = cmp::min(n, shanten_chiitoitsu(hand));
+        n = cmp::min(n, shanten_kokushi(hand));
+    }
+    n
+}
+
+pub fn shanten_chiitoitsu(hand: &TileSet) -> isize {
+    let n_pairs = hand.iter().filter(|e| **e >= 2).count() as isize;
+    let n_kinds = hand.iter().filter(|e| **e >= 1).count() as isize;
+    6 - n_pairs + cmp::max(7 - n_kinds, 0)
+}
+
+pub fn shanten_kokushi(hand: &TileSet) -> isize {
+    let terminals = (0..hand.len()).filter(|i| is_terminal_or_honor(*i));
+    let n_kinds = terminals.clone().filter(|i| hand.tile(*i) >= 1).count() as isize;
+    let has_pair = terminals.clone().any(|i| hand.tile(i) >= 2);
+    13 - n_kinds - has_pair as isize
+}
+
+// maximizes 2 * # of triads + # of partial blocks + (1 if a pair is left as the head).
+fn search(hand: &mut TileSet, i0: usize, n_max: usize, state: (usize, usize, bool), best: &mut usize) {
+    let (n_triads, n_partials, has_head) = state;
+    let i = match (i0..hand.len()).find(|i| hand.tile(*i) > 0) {
+        Some(i) => i,
+        None => {
+            let n_partials = cmp::min(n_partials, n_max - n_triads);
+            *best = cmp::max(*best, 2 * n_triads + n_partials + has_head as usize);
+            return;
+        }
+    };
+    let is_simple = i < 27;
+
+    if n_triads < n_max {
+        if hand.tile(i) >= 3 {
+            *hand.tile_mut(i) -= 3;
+            search(hand, i, n_max, (n_triads + 1, n_partials, has_head), best);
+            *hand.tile_mut(i) += 3;
+        }
+        if is_simple && i % 9 < 7 && hand.tile(i + 1) > 0 && hand.tile(i + 2) > 0 {
+            take(hand, &[i, i + 1, i + 2]);
+            search(hand, i, n_max, (n_triads + 1, n_partials, has_head), best);
+            give(hand, &[i, i + 1, i + 2]);
+        }
+    }
+    if hand.tile(i) >= 2 {
+        *hand.tile_mut(i) -= 2;
+        if !has_head {
+            search(hand, i, n_max, (n_triads, n_partials, true), best);
+        }
+        search(hand, i, n_max, (n_triads, n_partials + 1, has_head), best);
+        *hand.tile_mut(i) += 2;
+    }
+    if is_simple && i % 9 < 8 && hand.tile(i + 1) > 0 {
+        take(hand, &[i, i + 1]);
+        search(hand, i, n_max, (n_triads, n_partials + 1, has_head), best);
+        give(hand, &[i, i + 1]);
+    }
+    if is_simple && i % 9 < 7 && hand.tile(i + 2) > 0 {
+        take(hand, &[i, i + 2]);
+        search(hand, i, n_max, (n_triads, n_partials + 1, has_head), best);
+        give(hand, &[i, i + 2]);
+    }
+    let n = hand.tile(i);
+    *hand.tile_mut(i) = 0;
+    search(hand, i + 1, n_max, state, best);
+    *hand.tile_mut(i) = n;
+}
+
+fn take(hand: &mut TileSet, tiles: &[usize]) {
+    for i in tiles {
+        *hand.tile_mut(*i) -= 1;
+    }
+}
+
+fn give(hand: &mut TileSet, tiles: &[usize]) {
+    for i in tiles {
+        *hand.tile_mut(*i) += 1;
+    }
+}
+
+// the draws which reduce the shanten number of a 3n + 1 tile hand, with their # of copies in the wall.
+pub fn ukeire(hand: &TileSet, wall: &TileSet) -> Vec<(usize, usize)> {
+    let n = shanten(hand);
+    let mut hand = hand.clone();
+    let mut dst = Vec::new();
+    for i in 0..hand.len() {
+        if wall.tile(i) == 0 || hand.add(i).is_err() {
+            continue;
+        }
+        if shanten(&hand) < n {
+            dst.push((i, wall.tile(i) as usize));
+        }
+        hand.remove(i).unwrap();
+    }
+    dst
+}
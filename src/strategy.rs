@@ -0,0 +1,212 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::danger;
+use crate::game::*;
+use crate::playout;
+use crate::score::*;
+use crate::search;
+use crate::shanten;
+use crate::uct;
+
+// wins whenever it can, declares riichi whenever it can and never calls.  otherwise discards the tile which leaves
+// the lowest shanten number and then the most ukeire.
+#[derive(Clone, Debug, Default)]
+pub struct Ukeire;
+
+impl Strategy for Ukeire {
+    fn act(&mut self, view: &View, actions: &[Action]) -> Action {
+        if actions.contains(&Action::Tsumo) {
+            return Action::Tsumo;
+        }
+        let mut hand = view.hand.clone();
+        let mut candidates = Vec::new();
+        for a in actions.iter() {
+            if let Action::Discard(t) = *a {
+                *hand.tile_mut(t) -= 1;
+                candidates.push((shanten::shanten(&hand), t));
+                *hand.tile_mut(t) += 1;
+            }
+        }
+        let n_min = candidates.iter().map(|(n, _)| *n).min().unwrap();
+
+        let wall = view.table().wall(view.hand).unwrap_or_default();
+        let mut best: Option<(usize, usize)> = None;
+        for (_, t) in candidates.into_iter().filter(|(n, _)| *n == n_min) {
+            *hand.tile_mut(t) -= 1;
+            let n_ukeire: usize = shanten::ukeire(&hand, &wall).iter().map(|(_, n)| n).sum();
+            *hand.tile_mut(t) += 1;
+            if best.is_none_or(|(n, _)| n_ukeire > n) {
+                best = Some((n_ukeire, t));
+            }
+        }
+        let (_, t) = best.unwrap();
+        if actions.contains(&Action::Riichi(t)) {
+            Action::Riichi(t)
+        } else {
+            Action::Discard(t)
+        }
+    }
+
+    fn call(&mut self, _: &View, _: usize, _: usize, calls: &[Call]) -> Call {
+        ron_or_pass(calls)
+    }
+}
+
+// the engines below win whenever they can and never call either.
+fn ron_or_pass(calls: &[Call]) -> Call {
+    if calls.contains(&Call::Ron) {
+        Call::Ron
+    } else {
+        Call::Pass
+    }
+}
+
+// the legal discards, or `None` if there is no choice, e.g. after riichi.
+fn discards(actions: &[Action]) -> Option<Vec<usize>> {
+    let tiles: Vec<_> = actions
+        .iter()
+        .filter_map(|a| match *a {
+            Action::Discard(t) => Some(t),
+            _ => None,
+        })
+        .collect();
+    if tiles.len() > 1 {
+        Some(tiles)
+    } else {
+        None
+    }
+}
+
+// the legal discard with the lowest score, with riichi whenever it is legal.
+fn min_score(actions: &[Action], tiles: &[usize], scores: &[(usize, f64)]) -> Action {
+    let tile = scores
+        .iter()
+        .filter(|(t, _)| tiles.contains(t))
+        .min_by(|(_, s0), (_, s1)| s0.partial_cmp(s1).unwrap())
+        .map_or(*tiles.last().unwrap(), |(t, _)| *t);
+    if actions.contains(&Action::Riichi(tile)) {
+        Action::Riichi(tile)
+    } else {
+        Action::Discard(tile)
+    }
+}
+
+// `search::discard_tile_parallel()`.
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub depth: usize,
+}
+
+impl Strategy for Search {
+    fn act(&mut self, view: &View, actions: &[Action]) -> Action {
+        if actions.contains(&Action::Tsumo) {
+            return Action::Tsumo;
+        }
+        let tiles = match discards(actions) {
+            Some(t) => t,
+            None => return *actions.last().unwrap(),
+        };
+        let mut hand = view.hand.clone();
+        let mut wall = view.table().wall(view.hand).unwrap_or_default();
+        let (_, scores) = search::discard_tile_parallel(&mut hand, &mut wall, self.depth);
+        min_score(actions, &tiles, &scores)
+    }
+
+    fn call(&mut self, _: &View, _: usize, _: usize, calls: &[Call]) -> Call {
+        ron_or_pass(calls)
+    }
+}
+
+// `playout::discard_tile_parallel()` within the live wall.
+#[derive(Clone, Debug)]
+pub struct Playout {
+    pub depth: usize,
+    pub n_samples: usize,
+}
+
+impl Strategy for Playout {
+    fn act(&mut self, view: &View, actions: &[Action]) -> Action {
+        if actions.contains(&Action::Tsumo) {
+            return Action::Tsumo;
+        }
+        let tiles = match discards(actions) {
+            Some(t) => t,
+            None => return *actions.last().unwrap(),
+        };
+        let table = view.table();
+        let mut hand = view.hand.clone();
+        let mut wall = table.wall(view.hand).unwrap_or_default();
+        let n_draws = table.n_draws(&wall);
+        let (_, scores) = playout::discard_tile_parallel(
+            &mut hand,
+            &mut wall,
+            n_draws,
+            self.depth,
+            self.n_samples,
+            rand::random(),
+        );
+        min_score(actions, &tiles, &scores)
+    }
+
+    fn call(&mut self, _: &View, _: usize, _: usize, calls: &[Call]) -> Call {
+        ron_or_pass(calls)
+    }
+}
+
+// `uct::discard_tile()`, which also chooses whether to declare riichi.  the opponents may end the round first.
+#[derive(Clone, Debug)]
+pub struct Uct {
+    pub n_samples: usize,
+}
+
+impl Strategy for Uct {
+    fn act(&mut self, view: &View, actions: &[Action]) -> Action {
+        if actions.contains(&Action::Tsumo) {
+            return Action::Tsumo;
+        }
+        let tiles = match discards(actions) {
+            Some(t) => t,
+            None => return *actions.last().unwrap(),
+        };
+        let table = view.table();
+        let wall = table.wall(view.hand).unwrap_or_default();
+        let horizon = danger::horizon(&table, &wall);
+        let mut ctx = table.win_context(WinType::Tsumo);
+        ctx.riichi = view.player().riichi;
+        let choices = uct::discard_tile(
+            view.hand,
+            &view.player().melds,
+            &wall,
+            &horizon,
+            &ctx,
+            self.n_samples,
+            &mut rand::thread_rng(),
+        );
+        let best = choices
+            .iter()
+            .filter(|c| tiles.contains(&c.tile) && (!c.riichi || actions.contains(&Action::Riichi(c.tile))))
+            .max_by_key(|c| c.count);
+        match best {
+            Some(c) if c.riichi => Action::Riichi(c.tile),
+            Some(c) => Action::Discard(c.tile),
+            None => *actions.last().unwrap(),
+        }
+    }
+
+    fn call(&mut self, _: &View, _: usize, _: usize, calls: &[Call]) -> Call {
+        ron_or_pass(calls)
+    }
+}
+
+// the engines by the names used on the command line.
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy>> {
+    match name {
+        "ukeire" => Some(Box::new(Ukeire)),
+        "search" => Some(Box::new(Search { depth: 2 })),
+        "playout" => Some(Box::new(Playout {
+            depth: 1,
+            n_samples: 1 << 16,
+        })),
+        "uct" => Some(Box::new(Uct { n_samples: 1 << 16 })),
+        _ => None,
+    }
+}
@@ -0,0 +1,86 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::score::*;
+use crate::tile::*;
+
+pub const OPPONENT_TILES: usize = 3 * 13;
+pub const DEAD_WALL: usize = 14;
+
+#[derive(Clone, Debug, Default)]
+pub struct Opponent {
+    pub discards: TileSet,
+    pub riichi: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct TableState {
+    pub own_discards: TileSet,
+    // the discards whose owner is not given.
+    pub discards: TileSet,
+    // shimocha, toimen and kamicha.
+    pub opponents: [Opponent; 3],
+    pub melds: TileSet,
+    pub dora_indicators: TileSet,
+    // # of concealed tiles in the opponents' hands.
+    pub opponent_tiles: usize,
+    // # of tiles in the dead wall, including the dora indicators.
+    pub dead_wall: usize,
+    pub seat_wind: usize,
+    pub round_wind: usize,
+}
+
+impl Default for TableState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TableState {
+    pub fn new() -> Self {
+        TableState {
+            own_discards: TileSet::new(),
+            discards: TileSet::new(),
+            opponents: Default::default(),
+            melds: TileSet::new(),
+            dora_indicators: TileSet::new(),
+            opponent_tiles: OPPONENT_TILES,
+            dead_wall: DEAD_WALL,
+            seat_wind: 0,
+            round_wind: 0,
+        }
+    }
+
+    pub fn visible(&self) -> Result<TileSet, TileError> {
+        let mut v = self.own_discards.union(&self.discards)?;
+        for o in self.opponents.iter() {
+            v = v.union(&o.discards)?;
+        }
+        v.union(&self.melds)?.union(&self.dora_indicators)
+    }
+
+    pub fn wall(&self, hand: &TileSet) -> Result<TileSet, TileError> {
+        remaining_tiles(&[hand, &self.visible()?])
+    }
+
+    // # of tiles left to draw.  the live wall is a random subset of the unseen tiles `wall`.
+    pub fn n_live(&self, wall: &TileSet) -> usize {
+        let n_hidden = self.opponent_tiles + self.dead_wall.saturating_sub(self.dora_indicators.count());
+        wall.count().saturating_sub(n_hidden)
+    }
+
+    // # of our own draws left, sharing the live wall with three opponents.
+    pub fn n_draws(&self, wall: &TileSet) -> usize {
+        self.n_live(wall) / 4
+    }
+
+    pub fn win_context(&self, win_type: WinType) -> WinContext {
+        let mut ctx = WinContext::new(win_type);
+        ctx.seat_wind = self.seat_wind;
+        ctx.round_wind = self.round_wind;
+        for (i, n) in self.dora_indicators.iter().enumerate() {
+            for _ in 0..*n {
+                ctx.dora_indicators.push(i);
+            }
+        }
+        ctx
+    }
+}
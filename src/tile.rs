@@ -2,14 +2,40 @@
 use std::fmt::Write;
 use std::*;
 
-#[derive(Clone)]
+pub const N_KINDS: usize = 34;
+pub const N_COPIES: u8 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileError {
+    TooManyCopies(usize),
+    NotPresent(usize),
+}
+
+impl fmt::Display for TileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TileError::TooManyCopies(i) => write!(f, "more than {} copies of {}", N_COPIES, format_tile(i)),
+            TileError::NotPresent(i) => write!(f, "no {} to remove", format_tile(i)),
+        }
+    }
+}
+
+impl error::Error for TileError {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct TileSet {
-    tiles: [u8; 34],
+    tiles: [u8; N_KINDS],
+}
+
+impl Default for TileSet {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TileSet {
     pub fn new() -> Self {
-        TileSet { tiles: [0; 34] }
+        TileSet { tiles: [0; N_KINDS] }
     }
 
     pub fn tile(&self, i: usize) -> u8 {
@@ -44,9 +70,101 @@ impl TileSet {
         self.tiles.len()
     }
 
-    pub fn iter(&self) -> slice::Iter<u8> {
+    pub fn is_empty(&self) -> bool {
+        self.iter().all(|e| *e == 0)
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, u8> {
         self.tiles.iter()
     }
+
+    pub fn add(&mut self, i: usize) -> Result<(), TileError> {
+        if self.tiles[i] >= N_COPIES {
+            return Err(TileError::TooManyCopies(i));
+        }
+        self.tiles[i] += 1;
+        Ok(())
+    }
+
+    pub fn remove(&mut self, i: usize) -> Result<(), TileError> {
+        if self.tiles[i] == 0 {
+            return Err(TileError::NotPresent(i));
+        }
+        self.tiles[i] -= 1;
+        Ok(())
+    }
+
+    pub fn union(&self, other: &TileSet) -> Result<TileSet, TileError> {
+        let mut dst = TileSet::new();
+        for (i, (a, b)) in self.iter().zip(other.iter()).enumerate() {
+            let n = *a as usize + *b as usize;
+            if n > N_COPIES as usize {
+                return Err(TileError::TooManyCopies(i));
+            }
+            dst.tiles[i] = n as u8;
+        }
+        Ok(dst)
+    }
+
+    pub fn difference(&self, other: &TileSet) -> Result<TileSet, TileError> {
+        let mut dst = TileSet::new();
+        for (i, (a, b)) in self.iter().zip(other.iter()).enumerate() {
+            dst.tiles[i] = a.checked_sub(*b).ok_or(TileError::NotPresent(i))?;
+        }
+        Ok(dst)
+    }
+
+    pub fn is_subset(&self, other: &TileSet) -> bool {
+        self.iter().zip(other.iter()).all(|(a, b)| a <= b)
+    }
+
+    pub fn validate(&self) -> Result<(), TileError> {
+        match self.iter().position(|e| *e > N_COPIES) {
+            Some(i) => Err(TileError::TooManyCopies(i)),
+            None => Ok(()),
+        }
+    }
+}
+
+// the tiles not in any of `sets`, e.g. the wall seen from a hand and the visible tiles.
+pub fn remaining_tiles(sets: &[&TileSet]) -> Result<TileSet, TileError> {
+    let mut used = TileSet::new();
+    for set in sets {
+        used = used.union(set)?;
+    }
+    let mut dst = TileSet::new();
+    for (i, n) in used.iter().enumerate() {
+        dst.tiles[i] = N_COPIES - n;
+    }
+    Ok(dst)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Meld {
+    Chi(usize),
+    Pon(usize),
+    Kan(usize),
+    ClosedKan(usize),
+}
+
+impl Meld {
+    pub fn is_open(&self) -> bool {
+        !matches!(self, Meld::ClosedKan(_))
+    }
+
+    pub fn tiles(&self) -> TileSet {
+        let mut dst = TileSet::new();
+        match *self {
+            Meld::Chi(i) => {
+                for j in i..i + 3 {
+                    dst.tiles[j] += 1;
+                }
+            }
+            Meld::Pon(i) => dst.tiles[i] += 3,
+            Meld::Kan(i) | Meld::ClosedKan(i) => dst.tiles[i] += 4,
+        }
+        dst
+    }
 }
 
 pub fn format_tile(tile: usize) -> String {
@@ -88,43 +206,74 @@ pub fn format_tile_set(hand: &TileSet) -> String {
     buf
 }
 
-pub fn parse_tile_set(text: &str) -> Option<TileSet> {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    UnknownSuit(char),
+    DanglingDigits,
+    InvalidHonor(usize),
+    TooManyCopies(usize),
+    RedFive,
+    UnknownTile,
+    // a physical tile given twice, by its 136 tile ID.
+    DuplicateId(u32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub pos: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseErrorKind::UnknownSuit(c) => write!(f, "unknown suit letter '{}'", c),
+            ParseErrorKind::DanglingDigits => write!(f, "digits without a suit letter"),
+            ParseErrorKind::InvalidHonor(i) => write!(f, "no such honor tile {}z", i),
+            ParseErrorKind::TooManyCopies(i) => write!(f, "more than {} copies of {}", N_COPIES, format_tile(i)),
+            ParseErrorKind::RedFive => write!(f, "red fives are not supported"),
+            ParseErrorKind::UnknownTile => write!(f, "unknown tile"),
+            ParseErrorKind::DuplicateId(id) => write!(f, "tile ID {} given twice", id),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.kind, self.pos)
+    }
+}
+
+impl error::Error for ParseError {}
+
+pub fn parse_tile_set(text: &str) -> Result<TileSet, ParseError> {
+    let error = |pos, kind| ParseError { pos, kind };
     let mut hand = TileSet::new();
     let mut nums = Vec::new();
-    for c in text.chars() {
+    for (pos, c) in text.chars().enumerate() {
         match c {
-            '1'..='9' => nums.push(c.to_digit(10).unwrap() as usize),
-            'm' => {
-                for i in nums.drain(..) {
-                    *hand.simple_mut(0, i - 1) += 1;
-                }
-            }
-            'p' => {
-                for i in nums.drain(..) {
-                    *hand.simple_mut(1, i - 1) += 1;
-                }
-            }
-            's' => {
-                for i in nums.drain(..) {
-                    *hand.simple_mut(2, i - 1) += 1;
-                }
-            }
-            'z' => {
-                for i in nums.drain(..) {
-                    if i > 7 {
-                        return None;
+            '1'..='9' => nums.push((pos, c.to_digit(10).unwrap() as usize)),
+            '0' => return Err(error(pos, ParseErrorKind::RedFive)),
+            'm' | 'p' | 's' | 'z' => {
+                let t = ['m', 'p', 's', 'z'].iter().position(|e| *e == c).unwrap();
+                for (pos, i) in nums.drain(..) {
+                    if t == 3 && i > 7 {
+                        return Err(error(pos, ParseErrorKind::InvalidHonor(i)));
+                    }
+                    let tile = 9 * t + i - 1;
+                    if hand.add(tile).is_err() {
+                        return Err(error(pos, ParseErrorKind::TooManyCopies(tile)));
                     }
-                    *hand.honor_mut(i - 1) += 1;
                 }
             }
             ' ' | '\t' | '\n' => (),
-            _ => return None,
+            _ => return Err(error(pos, ParseErrorKind::UnknownSuit(c))),
         }
     }
-    if !nums.is_empty() {
-        return None;
+    if let Some((pos, _)) = nums.first() {
+        return Err(error(*pos, ParseErrorKind::DanglingDigits));
     }
-    Some(hand)
+    Ok(hand)
 }
 
 const WEIGHT_PAIR: usize = 2;
@@ -166,6 +315,7 @@ pub fn count_head_and_triad(hand: &mut TileSet, allow_headless: bool) -> usize {
     n_total
 }
 
+#[allow(clippy::identity_op)]
 pub fn count_triad_simple(hand: &mut TileSet, t: usize, i0: usize) -> usize {
     let mut n_total = 0;
     for i in i0..7 {
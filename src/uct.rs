@@ -1,67 +1,147 @@
 // (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::playout::{best_score, Horizon, RIICHI_DEPOSIT};
+use crate::score::*;
+use crate::shanten;
 use crate::tile::*;
 use std::*;
 
 struct DiscardNode {
     count: usize,
     value: usize,
+    points: f64,
     children: [Option<Box<DrawNode>>; 34],
+    // discards with a riichi declaration, allowed when the rest of the hand is in tenpai.
+    riichi: [Option<Box<RiichiNode>>; 34],
 }
 
 struct DrawNode {
     count: usize,
     value: usize,
+    points: f64,
     children: [Option<Box<DiscardNode>>; 34],
 }
 
+// the hand is locked after riichi: every draw but a winning one is discarded.
+struct RiichiNode {
+    count: usize,
+    value: usize,
+    points: f64,
+}
+
+// the discards of each hand which leave it in tenpai, shared by the nodes of the same hand.
+type TenpaiDiscards = collections::HashMap<TileSet, [bool; 34]>;
+
+fn tenpai_discards(hand: &mut TileSet) -> [bool; 34] {
+    let mut dst = [false; 34];
+    // no discard reduces the shanten number of the hand.
+    if shanten::shanten(hand) > 0 {
+        return dst;
+    }
+    for (i, e) in dst.iter_mut().enumerate() {
+        if hand.tile(i) > 0 {
+            *hand.tile_mut(i) -= 1;
+            *e = shanten::shanten(hand) == 0;
+            *hand.tile_mut(i) += 1;
+        }
+    }
+    dst
+}
+
+// the result of each root action, with the mean # of turns to win and the mean points.
+#[derive(Clone, Copy, Debug)]
+pub struct Action {
+    pub tile: usize,
+    pub riichi: bool,
+    pub count: usize,
+    pub turns: f64,
+    pub points: f64,
+}
+
 impl DiscardNode {
     fn new() -> Self {
         DiscardNode {
             count: 0,
             value: 0,
+            points: 0.0,
             children: arr_macro::arr![None; 34],
+            riichi: arr_macro::arr![None; 34],
         }
     }
 
-    fn sample(&mut self, hand: &mut TileSet, wall: &mut Vec<usize>) -> usize {
+    fn sample(
+        &mut self,
+        hand: &mut TileSet,
+        melds: &[Meld],
+        wall: &mut Vec<usize>,
+        last: usize,
+        ctx: &WinContext,
+        tenpai: &mut TenpaiDiscards,
+    ) -> (usize, f64) {
         if self.count == usize::MAX {
-            return 0;
+            return (0, self.points);
         }
         let n_tiles = hand.count();
         if count_head_and_triad(hand, false) >= n_tiles {
             self.count = usize::MAX;
             self.value = 0;
-            return 0;
+            self.points = score_points(hand, melds, last, ctx);
+            return (0, self.points);
         }
 
-        let v = 1 + if self.count < n_tiles {
-            playout(hand, wall, n_tiles)
+        let (v, p) = if self.count < n_tiles {
+            playout(hand, melds, wall, n_tiles, ctx)
         } else {
+            let can_riichi = if ctx.riichi || melds.iter().any(|m| m.is_open()) {
+                [false; 34]
+            } else {
+                *tenpai.entry(hand.clone()).or_insert_with(|| tenpai_discards(hand))
+            };
+            // the discards are chosen by the # of turns to win, riichi and dama together, and then riichi or dama by
+            // the points, as riichi never wins faster but may win more.
             let t = f64::sqrt(2.0) * n_tiles as f64 * f64::sqrt(f64::ln((self.count - n_tiles) as f64));
-            let mut min_tile = usize::MAX;
+            let ucb = |count: usize, value: usize| match count {
+                0 => -f64::INFINITY,
+                _ => value as f64 / count as f64 - t / f64::sqrt(count as f64),
+            };
+            let mut tile = usize::MAX;
             let mut min_score = f64::INFINITY;
-            for i in 0..34 {
+            for i in 0..hand.len() {
                 if hand.tile(i) == 0 {
                     continue;
                 }
-                let score = match self.children[i] {
-                    Some(ref n) => n.value as f64 / n.count as f64 - t / f64::sqrt(n.count as f64),
-                    None => -f64::INFINITY,
-                };
+                let (count, value) = [
+                    self.children[i].as_ref().map(|n| (n.count, n.value)),
+                    self.riichi[i].as_ref().map(|n| (n.count, n.value)),
+                ]
+                .iter()
+                .flatten()
+                .fold((0, 0), |(c, v), (nc, nv)| (c + nc, v + nv));
+                let score = ucb(count, value);
                 if score < min_score {
-                    min_tile = i;
+                    tile = i;
                     min_score = score;
                 }
             }
-
-            *hand.tile_mut(min_tile) -= 1;
-            self.children[min_tile]
-                .get_or_insert_with(|| Box::new(DrawNode::new()))
-                .sample(hand, wall)
+            let riichi = can_riichi[tile]
+                && choose_riichi(
+                    self.children[tile].as_ref().map_or((0, 0.0), |n| (n.count, n.points)),
+                    self.riichi[tile].as_ref().map_or((0, 0.0), |n| (n.count, n.points)),
+                );
+            *hand.tile_mut(tile) -= 1;
+            if riichi {
+                self.riichi[tile]
+                    .get_or_insert_with(|| Box::new(RiichiNode::new()))
+                    .sample(hand, melds, wall, ctx)
+            } else {
+                self.children[tile]
+                    .get_or_insert_with(|| Box::new(DrawNode::new()))
+                    .sample(hand, melds, wall, ctx, tenpai)
+            }
         };
         self.count += 1;
-        self.value += v;
-        v
+        self.value += 1 + v;
+        self.points += p;
+        (1 + v, p)
     }
 }
 
@@ -70,60 +150,228 @@ impl DrawNode {
         DrawNode {
             count: 0,
             value: 0,
+            points: 0.0,
             children: arr_macro::arr![None; 34],
         }
     }
 
-    fn sample(&mut self, hand: &mut TileSet, wall: &mut Vec<usize>) -> usize {
-        let tile = wall.pop().unwrap();
-        *hand.tile_mut(tile) += 1;
-        let v = self.children[tile]
-            .get_or_insert_with(|| Box::new(DiscardNode::new()))
-            .sample(hand, wall);
+    fn sample(
+        &mut self,
+        hand: &mut TileSet,
+        melds: &[Meld],
+        wall: &mut Vec<usize>,
+        ctx: &WinContext,
+        tenpai: &mut TenpaiDiscards,
+    ) -> (usize, f64) {
+        let (v, p) = match wall.pop() {
+            // the round has ended: the rest of the wall counts as turns without a win.
+            Some(tile) if tile >= N_KINDS => (wall.len(), 0.0),
+            Some(tile) => {
+                *hand.tile_mut(tile) += 1;
+                self.children[tile]
+                    .get_or_insert_with(|| Box::new(DiscardNode::new()))
+                    .sample(hand, melds, wall, tile, ctx, tenpai)
+            }
+            None => (0, 0.0),
+        };
         self.count += 1;
         self.value += v;
-        v
+        self.points += p;
+        (v, p)
+    }
+}
+
+impl RiichiNode {
+    fn new() -> Self {
+        RiichiNode {
+            count: 0,
+            value: 0,
+            points: 0.0,
+        }
+    }
+
+    fn sample(&mut self, hand: &mut TileSet, melds: &[Meld], wall: &[usize], ctx: &WinContext) -> (usize, f64) {
+        let mut ctx = ctx.clone();
+        ctx.riichi = true;
+        let n_tiles = hand.count() + 1;
+        let mut result = (wall.iter().filter(|t| **t < N_KINDS).count(), -RIICHI_DEPOSIT);
+        for (i, tile) in wall.iter().rev().enumerate() {
+            if *tile >= N_KINDS {
+                break;
+            }
+            *hand.tile_mut(*tile) += 1;
+            if count_head_and_triad(hand, false) >= n_tiles {
+                ctx.ippatsu = i == 0;
+                result = (i, score_points(hand, melds, *tile, &ctx));
+                break;
+            }
+            *hand.tile_mut(*tile) -= 1;
+        }
+        self.count += 1;
+        self.value += result.0;
+        self.points += result.1;
+        result
+    }
+}
+
+// chooses riichi over dama by the UCB of the mean points, given (# of visits, sum of points) of each.
+fn choose_riichi(dama: (usize, f64), riichi: (usize, f64)) -> bool {
+    if dama.0 == 0 || riichi.0 == 0 {
+        return riichi.0 < dama.0;
+    }
+    let mean = |(count, points): (usize, f64)| points / count as f64;
+    let scale = f64::max(f64::max(mean(dama).abs(), mean(riichi).abs()), RIICHI_DEPOSIT);
+    let t = f64::sqrt(2.0) * scale * f64::sqrt(f64::ln((dama.0 + riichi.0) as f64));
+    let ucb = |n: (usize, f64)| mean(n) + t / f64::sqrt(n.0 as f64);
+    ucb(riichi) > ucb(dama)
+}
+
+fn score_points(hand: &TileSet, melds: &[Meld], last: usize, ctx: &WinContext) -> f64 {
+    if last >= N_KINDS {
+        return 0.0;
     }
+    score_hand(hand, melds, last, ctx).map_or(0.0, |s| s.points as f64)
 }
 
-fn playout(hand: &mut TileSet, wall: &mut Vec<usize>, n_tiles: usize) -> usize {
+fn playout(hand: &mut TileSet, melds: &[Meld], wall: &[usize], n_tiles: usize, ctx: &WinContext) -> (usize, f64) {
     for (i, tile) in wall.iter().rev().enumerate() {
+        if *tile >= N_KINDS {
+            return (wall.len() - 1, 0.0);
+        }
         *hand.tile_mut(*tile) += 1;
         if count_head_and_triad(hand, false) >= n_tiles {
-            return i;
+            return (
+                i,
+                best_score(hand, melds, *tile, n_tiles, ctx).map_or(0.0, |s| s.points as f64),
+            );
         }
     }
-    unreachable!();
+    (wall.len(), 0.0)
 }
 
-pub fn discard_tile<R: rand::Rng>(
-    hand: &TileSet,
-    wall: &TileSet,
-    n_samples: usize,
-    rng: &mut R,
-) -> Vec<(usize, usize, f64)> {
-    let mut root = DiscardNode::new();
+// a search tree kept across the turns.  after a discard and the next draw, the subtree of them is searched on the
+// same wall, so its samples are reused.
+pub struct Tree {
+    root: DiscardNode,
+    hand: TileSet,
+    melds: Vec<Meld>,
+    // the unseen tiles, one for each.
+    wall: Vec<usize>,
+    horizon: Horizon,
+    ctx: WinContext,
+    tenpai: TenpaiDiscards,
+}
 
-    let mut acc = Vec::new();
-    for _ in 0..n_samples {
-        acc.clear();
+impl Tree {
+    // we draw `horizon.n_draws` tiles from `wall`, and an opponent may win before each of them, which ends the round.
+    pub fn new(hand: &TileSet, melds: &[Meld], wall: &TileSet, horizon: &Horizon, ctx: &WinContext) -> Self {
+        let mut ctx = ctx.clone();
+        ctx.win_type = WinType::Tsumo;
+        let mut acc = Vec::new();
         for i in 0..wall.len() {
             for _ in 0..wall.tile(i) {
                 acc.push(i);
             }
         }
-        rand::seq::SliceRandom::shuffle(&mut acc[..], rng);
-        root.sample(&mut hand.clone(), &mut acc);
+        Tree {
+            root: DiscardNode::new(),
+            hand: hand.clone(),
+            melds: melds.to_vec(),
+            wall: acc,
+            horizon: *horizon,
+            ctx,
+            tenpai: TenpaiDiscards::new(),
+        }
     }
 
-    let mut discards = Vec::new();
-    for (i, node) in root.children.iter().enumerate() {
-        let node = match node {
-            Some(n) => n,
-            None => continue,
+    // # of samples the root has, including the ones reused.
+    pub fn n_samples(&self) -> usize {
+        match self.root.count {
+            usize::MAX => 0,
+            n => n,
+        }
+    }
+
+    pub fn sample<R: rand::Rng>(&mut self, n_samples: usize, rng: &mut R) {
+        // the ura dora indicators are drawn from the unseen tiles besides the live ones, as in `playout_locked()`.
+        let n_ura = self.ctx.dora_indicators.len();
+        let horizon = Horizon {
+            n_draws: cmp::min(self.horizon.n_draws, self.wall.len().saturating_sub(n_ura)),
+            ..self.horizon
         };
-        let score = node.value as f64 / node.count as f64;
-        discards.push((i, node.count, score));
+        let mut ctx = self.ctx.clone();
+        for _ in 0..n_samples {
+            let (draws, _) = rand::seq::SliceRandom::partial_shuffle(&mut self.wall[..], rng, horizon.n_draws + n_ura);
+            let (live, ura) = draws.split_at(horizon.n_draws);
+            ctx.ura_indicators = ura.to_vec();
+            let mut live = live.to_vec();
+            // the round ends at `N_KINDS` if an opponent wins first.
+            let n = horizon.sample(rng);
+            if n < live.len() {
+                live.insert(live.len() - n, N_KINDS);
+            }
+            self.root.sample(
+                &mut self.hand.clone(),
+                &self.melds,
+                &mut live,
+                N_KINDS,
+                &ctx,
+                &mut self.tenpai,
+            );
+        }
+    }
+
+    // the discard of the action counts as a turn, as in the playouts.
+    pub fn actions(&self) -> Vec<Action> {
+        let mut discards = Vec::new();
+        for i in 0..34 {
+            if let Some(ref node) = self.root.children[i] {
+                discards.push(Action {
+                    tile: i,
+                    riichi: false,
+                    count: node.count,
+                    turns: 1.0 + node.value as f64 / node.count as f64,
+                    points: node.points / node.count as f64,
+                });
+            }
+            if let Some(ref node) = self.root.riichi[i] {
+                discards.push(Action {
+                    tile: i,
+                    riichi: true,
+                    count: node.count,
+                    turns: 1.0 + node.value as f64 / node.count as f64,
+                    points: node.points / node.count as f64,
+                });
+            }
+        }
+        discards
+    }
+
+    // moves to the hand after discarding `discard` and drawing `draw` from the wall, keeping the subtree of them.
+    pub fn advance(&mut self, discard: usize, draw: usize) {
+        *self.hand.tile_mut(discard) -= 1;
+        *self.hand.tile_mut(draw) += 1;
+        if let Some(i) = self.wall.iter().position(|t| *t == draw) {
+            self.wall.swap_remove(i);
+        }
+        self.horizon.n_draws = self.horizon.n_draws.saturating_sub(1);
+        let node = self.root.children[discard]
+            .take()
+            .and_then(|mut n| n.children[draw].take());
+        self.root = node.map_or_else(DiscardNode::new, |n| *n);
     }
-    discards
+}
+
+pub fn discard_tile<R: rand::Rng>(
+    hand: &TileSet,
+    melds: &[Meld],
+    wall: &TileSet,
+    horizon: &Horizon,
+    ctx: &WinContext,
+    n_samples: usize,
+    rng: &mut R,
+) -> Vec<Action> {
+    let mut tree = Tree::new(hand, melds, wall, horizon, ctx);
+    tree.sample(n_samples, rng);
+    tree.actions()
 }
@@ -0,0 +1,65 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use crate::decompose::*;
+use crate::score;
+use crate::tile::*;
+
+#[derive(Clone, Debug)]
+pub struct WinningTile {
+    pub tile: usize,
+    pub shapes: Vec<Wait>,
+    // # of copies left in the wall.
+    pub n_live: usize,
+}
+
+// the tiles which complete a 3n + 1 tile hand, with the shapes they are waited in.
+pub fn winning_tiles(hand: &TileSet, wall: &TileSet) -> Vec<WinningTile> {
+    let mut hand = hand.clone();
+    let mut dst = Vec::new();
+    for tile in 0..hand.len() {
+        if hand.add(tile).is_err() {
+            continue;
+        }
+        let mut shapes = Vec::new();
+        for blocks in complete_decompositions(&hand) {
+            shapes.extend(blocks.iter().filter_map(|b| wait_shape(b, tile)));
+        }
+        if score::is_chiitoitsu(&hand) || score::is_kokushi(&hand) {
+            shapes.push(Wait::Tanki);
+        }
+        hand.remove(tile).unwrap();
+
+        if !shapes.is_empty() {
+            shapes.sort_unstable();
+            shapes.dedup();
+            dst.push(WinningTile {
+                tile,
+                shapes,
+                n_live: wall.tile(tile) as usize,
+            });
+        }
+    }
+    dst
+}
+
+// a single wait on both ends of a run of four, e.g. 1234m waiting on 1m and 4m.
+pub fn is_nobetan(tiles: &[WinningTile], w: &WinningTile) -> bool {
+    let is_tanki = |t: usize| tiles.iter().any(|e| e.tile == t && e.shapes.contains(&Wait::Tanki));
+    w.tile < 27
+        && is_tanki(w.tile)
+        && ((w.tile % 9 >= 3 && is_tanki(w.tile - 3)) || (w.tile % 9 < 6 && is_tanki(w.tile + 3)))
+}
+
+pub fn is_furiten(tiles: &[WinningTile], own_discards: &TileSet) -> bool {
+    tiles.iter().any(|w| own_discards.tile(w.tile) > 0)
+}
+
+pub fn format_wait(tiles: &[WinningTile], w: &WinningTile, shape: Wait) -> &'static str {
+    match shape {
+        Wait::Ryanmen => "ryanmen",
+        Wait::Kanchan => "kanchan",
+        Wait::Penchan => "penchan",
+        Wait::Shanpon => "shanpon",
+        Wait::Tanki if is_nobetan(tiles, w) => "nobetan",
+        Wait::Tanki => "tanki",
+    }
+}
@@ -0,0 +1,73 @@
+/* (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License. */
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+#include "teff.h"
+
+static void tile_sets(void) {
+	size_t pos = 0;
+	TeffTileSet *hand = teff_tile_set_parse("123m456p789s1122z", &pos);
+	assert(hand != NULL);
+	assert(teff_tile_set_count(hand) == 13);
+	assert(teff_tile_set_get(hand, 27) == 2);
+	assert(teff_tile_set_get(hand, 34) == TEFF_ERROR_TILE);
+	assert(teff_tile_set_set(hand, 33, 5) == TEFF_ERROR_TILE);
+
+	char buf[64];
+	size_t n = teff_tile_set_format(hand, buf, sizeof(buf));
+	assert(n == strlen("123m 456p 789s 1122z") && strcmp(buf, "123m 456p 789s 1122z") == 0);
+	assert(teff_tile_set_format(hand, buf, 5) == n && strcmp(buf, "123m") == 0);
+
+	TeffTileSet *copy = teff_tile_set_clone(hand);
+	assert(teff_tile_set_set(copy, 0, 0) == TEFF_OK);
+	assert(teff_tile_set_count(copy) == 12 && teff_tile_set_count(hand) == 13);
+	teff_tile_set_free(copy);
+	teff_tile_set_free(hand);
+
+	assert(teff_tile_set_parse("12x", &pos) == NULL && pos == 2);
+	teff_tile_set_free(NULL);
+}
+
+static void shanten(void) {
+	TeffTileSet *hand = teff_tile_set_parse("123m456p789s1122z", NULL);
+	TeffTileSet *visible = teff_tile_set_parse("1z", NULL);
+	size_t counts[TEFF_N_KINDS];
+	assert(teff_shanten(hand) == 0);
+	assert(teff_ukeire(hand, NULL, counts) == 4);
+	assert(counts[27] == 2 && counts[28] == 2 && counts[0] == 0);
+	assert(teff_ukeire(hand, visible, NULL) == 3);
+	assert(teff_shanten(NULL) == TEFF_ERROR_NULL);
+	teff_tile_set_free(visible);
+	teff_tile_set_free(hand);
+}
+
+static void analyze(void) {
+	TeffTileSet *hand = teff_tile_set_parse("123m456p789s11223z", NULL);
+	TeffConfig config;
+	TeffDiscard results[2 * TEFF_N_KINDS];
+	teff_config_default(&config);
+	config.depth = 1;
+	int n = teff_analyze_discards(hand, NULL, &config, results, 2 * TEFF_N_KINDS);
+	assert(n == 12);
+	assert(results[0].tile == 29 && !results[0].riichi);
+	assert(results[0].score <= results[n - 1].score);
+
+	config.engine = TEFF_ENGINE_UCT;
+	config.n_samples = 4096;
+	n = teff_analyze_discards(hand, NULL, &config, results, 1);
+	assert(n >= 12 && results[0].count > 0);
+
+	config.engine = 9;
+	assert(teff_analyze_discards(hand, NULL, &config, results, 1) == TEFF_ERROR_ENGINE);
+	teff_tile_set_set(hand, 0, 0);
+	assert(teff_analyze_discards(hand, NULL, &config, results, 1) == TEFF_ERROR_HAND);
+	teff_tile_set_free(hand);
+}
+
+int main(void) {
+	tile_sets();
+	shanten();
+	analyze();
+	printf("ok\n");
+	return 0;
+}
@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// builds and runs tests/c/capi.c against the cdylib built for the tests, which is next to the test executable.
+#[test]
+#[cfg_attr(not(has_cc), ignore = "no C compiler")]
+fn c_program() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let out = lib_dir.join("capi_test");
+    let status = Command::new(std::env::var("CC").unwrap_or_else(|_| "cc".to_string()))
+        .arg(root.join("tests/c/capi.c"))
+        .arg("-I")
+        .arg(root.join("include"))
+        .arg("-L")
+        .arg(lib_dir)
+        .args(["-lteff", "-o"])
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = Command::new(&out).env("LD_LIBRARY_PATH", lib_dir).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, b"ok\n");
+}
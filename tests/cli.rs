@@ -0,0 +1,185 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+fn teff(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_teff")).args(args).output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn commands() {
+    let text = teff(&["shanten", "123m456p789s1122z"]);
+    assert!(text.contains("shanten = 0, ukeire = 4: 1z 2z"));
+
+    let text = teff(&["waits", "-o", "1z", "123m456p789s1122z"]);
+    assert!(text.contains("3 live tiles, furiten"));
+
+    // the hands without a command are analyzed, as before.
+    let text = teff(&["--engine", "search", "--depth", "1", "123m456p789s11223z"]);
+    assert!(text.contains("depth = 1:\n    3z"));
+    assert!(!text.contains("depth = 2"));
+    assert!(!text.contains("playout"));
+
+    assert!(teff(&["help"]).starts_with("Usage:"));
+    assert!(teff(&["analyze", "--engine", "foo"]).starts_with("Missing engine"));
+}
+
+// the syntax errors point at the wrong character.
+#[test]
+fn syntax_error() {
+    let text = teff(&["shanten", "1234x"]);
+    assert!(text.starts_with("Syntax error: 1234x\n                  ^ unknown suit letter 'x'\n"));
+
+    let text = teff(&["11111m"]);
+    assert!(text.contains("Syntax error: 11111m\n                  ^ more than 4 copies of 1m\n"));
+}
+
+// the table options take their tiles out of the wall, and `--riichi` the deal-in rate.
+#[test]
+fn table_options() {
+    let hand = "123m456p789s1122z";
+    assert!(teff(&["waits", hand]).contains("4 live tiles"));
+    let text = teff(&["waits", "-d", "1z", "-m", "2z", "-i", "1z", hand]);
+    assert!(text.contains("Visible: 112z \n"));
+    assert!(text.contains("1 live tiles"));
+    assert!(text.contains("2z  1 12000     - shanpon"));
+    let text = teff(&["waits", "-1", "1z", "-2", "2z", "-3", "9m", hand]);
+    assert!(text.contains("Visible: 9m 12z \n"));
+    assert!(text.contains("2 live tiles"));
+
+    let args = ["--engine", "search", "--depth", "1", "-3", "9m1p", "123m456p789s11223z"];
+    let text = teff(&args);
+    assert!(text.contains("live wall = 67"));
+    assert!(text.contains("\n    4p 0.000 "));
+    let text = teff(&[&["--riichi", "3"], &args[..]].concat());
+    assert!(text.contains("\n    4p 0.037 "));
+
+    assert!(teff(&["--riichi", "4", hand]).starts_with("Missing opponents"));
+    assert!(teff(&["waits", "-d"]).starts_with("Missing tiles after -d"));
+}
+
+// options alone run the benchmark, which plays hands until it is stopped.
+#[test]
+fn options_alone() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_teff"))
+        .args(["--seed", "1", "--engine", "search", "--depth", "1"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    assert!(lines.any(|l| l.unwrap().starts_with("N = 1")));
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn seed() {
+    for engine in ["playout", "uct"] {
+        let args = [
+            "--engine",
+            engine,
+            "--samples",
+            "256",
+            "--seed",
+            "1",
+            "123m456p789s11223z",
+        ];
+        assert_eq!(teff(&args), teff(&args));
+    }
+}
+
+#[test]
+fn formats() {
+    let args = [
+        "--json",
+        "--engine",
+        "playout",
+        "--samples",
+        "4096",
+        "123m456p789s11223z",
+        "123m",
+    ];
+    let text = teff(&args);
+    let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["hand"], "123m 456p 789s 11223z");
+    assert_eq!(lines[0]["params"]["n_samples"], 4096);
+    let sections = lines[0]["sections"].as_array().unwrap();
+    assert!(sections.iter().all(|s| s["seconds"].is_f64()));
+    let choice = &sections[0]["choices"][0];
+    assert_eq!(sections[0]["engine"], "playout");
+    assert!(choice["turns"].as_f64().unwrap() > 0.0);
+    assert!(choice["turns_std_err"].as_f64().unwrap() > 0.0);
+    assert!(lines[1]["error"].is_string());
+
+    let text = teff(&["--csv", "--engine", "search", "--depth", "1", "123m456p789s11223z"]);
+    let mut lines = text.lines();
+    assert!(lines.next().unwrap().starts_with("hand,wall,"));
+    assert!(lines.any(|l| l.contains(",search,1,,") && l.contains(",3z,false,non_melds,")));
+}
+
+#[test]
+fn batch() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_teff"))
+        .args(["batch", "--engine", "search", "--depth", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = "123m456p789s11223z -d 1m9p\n\n# comment\n123m456p789s1122z --riichi 2 -2 5m\n12x\n";
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("3 positions done"));
+
+    let text = String::from_utf8(output.stdout).unwrap();
+    let mut lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    lines.sort_by_key(|l| l["line"].as_u64());
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["line"], 1);
+    assert_eq!(lines[0]["n_unseen"], 120);
+    assert_eq!(lines[1]["line"], 4);
+    assert_eq!(lines[1]["hand"], "123m 456p 789s 1122z");
+    assert_eq!(lines[2]["line"], 5);
+    assert!(lines[2]["error"].is_string());
+}
+
+// the results of each line with `--seed`, without the times.
+fn batch_results(args: &[&str], input: &str) -> Vec<serde_json::Value> {
+    fn strip(v: &mut serde_json::Value) {
+        match v {
+            serde_json::Value::Object(o) => {
+                o.remove("seconds");
+                o.values_mut().for_each(strip);
+            }
+            serde_json::Value::Array(a) => a.iter_mut().for_each(strip),
+            _ => (),
+        }
+    }
+    let mut child = Command::new(env!("CARGO_BIN_EXE_teff"))
+        .arg("batch")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+    let mut lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    lines.iter_mut().for_each(strip);
+    lines.sort_by_key(|l| l["line"].as_u64());
+    lines
+}
+
+#[test]
+fn batch_seed() {
+    let args = ["--engine", "playout", "--samples", "256", "--seed", "1"];
+    let input = "123m456p789s11223z\n123m456p789s1122z\n1122z123m456p789s3z -d 1m\n";
+    let lines = batch_results(&args, input);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines, batch_results(&args, input));
+}
@@ -0,0 +1,90 @@
+use teff::danger::*;
+use teff::table::*;
+use teff::tile::*;
+
+fn tiles(text: &str) -> TileSet {
+    parse_tile_set(text).unwrap()
+}
+
+fn tile(text: &str) -> usize {
+    tiles(text).iter().position(|n| *n > 0).unwrap()
+}
+
+fn opponent(discards: &str, riichi: bool) -> Opponent {
+    Opponent {
+        discards: tiles(discards),
+        riichi,
+    }
+}
+
+// the safety of `tile` against an opponent who discarded `discards`, with `visible` tiles seen elsewhere.
+fn classify(tile_text: &str, discards: &str, visible: &str) -> Safety {
+    let mut table = TableState::new();
+    table.discards = tiles(visible);
+    table.opponents[0] = opponent(discards, true);
+    let wall = table.wall(&TileSet::new()).unwrap();
+    safety(tile(tile_text), &wall, &table.opponents[0])
+}
+
+#[test]
+fn classification() {
+    assert_eq!(classify("4m", "4m", ""), Safety::Genbutsu);
+    // 1m and 7m are suji of 4m, 5m needs both 2m and 8m.
+    assert_eq!(classify("1m", "4m", ""), Safety::Suji);
+    assert_eq!(classify("7m", "4m", ""), Safety::Suji);
+    assert_eq!(classify("5m", "4m", ""), Safety::Unsafe);
+    assert_eq!(classify("5m", "2m8m", ""), Safety::Suji);
+    assert_eq!(classify("5m", "2m", ""), Safety::Unsafe);
+
+    // 3m waits only in 45m, which is gone with the four 4m (kabe).
+    assert_eq!(classify("3m", "", "4444m"), Safety::NoChance);
+    assert_eq!(classify("3m", "", "444m"), Safety::OneChance);
+    assert_eq!(classify("3m", "", "44m"), Safety::Unsafe);
+    // 5m is safe from 34m by suji and from 67m by kabe.
+    assert_eq!(classify("5m", "2m", "6666m"), Safety::NoChance);
+
+    assert_eq!(classify("5z", "", "5555z"), Safety::NoChance);
+    assert_eq!(classify("5z", "", "555z"), Safety::Unsafe);
+    assert!(Safety::Genbutsu < Safety::Suji && Safety::Suji < Safety::Unsafe);
+}
+
+#[test]
+fn deal_in_rates() {
+    assert_eq!(tenpai_rate(&opponent("", true)), 1.0);
+    assert_eq!(tenpai_rate(&opponent("", false)), 0.0);
+    assert_eq!(round_end_rate(&TableState::new()), 0.0);
+
+    let hand = tiles("123m456p23789s115z");
+    let mut table = TableState::new();
+    table.opponents[1] = opponent("1z9m9p", true);
+    let dangers = discard_danger(&hand, &table).unwrap();
+    assert_eq!(dangers.len(), 13);
+    let d = |t: &str| dangers.iter().find(|d| d.tile == tile(t)).unwrap();
+    assert_eq!(d("1z").safety[1], Safety::Genbutsu);
+    assert_eq!(d("1z").deal_in, 0.0);
+    // only toimen is in tenpai, surely in riichi.
+    assert!(dangers.iter().all(|d| (d.deal_in - d.rates[1]).abs() < 1e-12));
+    assert!(d("5z").deal_in > 0.0 && d("5z").deal_in < d("3m").deal_in);
+    assert!(round_end_rate(&table) > 0.0);
+}
+
+#[test]
+fn push_or_fold() {
+    let hand = tiles("111235m456p23789s");
+    let mut table = TableState::new();
+    table.opponents[1] = opponent("1m9m9p5z", true);
+    let ranks = push_fold(&hand, &[], &table, 1 << 8, 1).unwrap();
+    assert_eq!(ranks.len(), 12);
+    assert!(ranks.windows(2).all(|w| w[0].delta >= w[1].delta));
+    for e in ranks.iter() {
+        assert!((e.delta - ((1.0 - e.deal_in) * e.points - e.loss)).abs() < 1e-9);
+    }
+    // folding discards the genbutsu 1m, and the discards better than it are pushes.
+    let fold = ranks.iter().find(|e| e.tile == tile("1m")).unwrap();
+    assert_eq!((fold.deal_in, fold.loss, fold.push), (0.0, 0.0, false));
+    assert!(ranks.iter().all(|e| e.push == (e.delta > fold.delta)));
+
+    let again = push_fold(&hand, &[], &table, 1 << 8, 1).unwrap();
+    let deltas = |r: &[PushFold]| r.iter().map(|e| (e.tile, e.delta)).collect::<Vec<_>>();
+    assert_eq!(deltas(&ranks), deltas(&again));
+}
@@ -0,0 +1,71 @@
+use teff::decompose::*;
+use teff::tile::*;
+
+fn tiles(text: &str) -> TileSet {
+    parse_tile_set(text).unwrap()
+}
+
+fn formatted(decompositions: &[Vec<Block>]) -> Vec<String> {
+    decompositions.iter().map(|blocks| format_blocks(blocks)).collect()
+}
+
+#[test]
+fn decomposition_counts() {
+    assert_eq!(formatted(&decompositions(&tiles("12m"))), ["12m", "1m 2m"]);
+    assert_eq!(
+        formatted(&decompositions(&tiles("112m"))),
+        ["11m 2m", "12m 1m", "1m 1m 2m"]
+    );
+    // honors make no sequences nor partial blocks.
+    assert_eq!(formatted(&decompositions(&tiles("123z"))), ["1z 2z 3z"]);
+    assert_eq!(decompositions(&TileSet::new()), vec![Vec::<Block>::new()]);
+
+    // every partition covers the hand exactly.
+    let hand = tiles("1123m");
+    let all = decompositions(&hand);
+    assert_eq!(all.len(), 8);
+    for blocks in all.iter() {
+        let mut sum = TileSet::new();
+        blocks.iter().for_each(|b| sum = sum.union(&b.tiles()).unwrap());
+        assert_eq!(sum, hand);
+    }
+}
+
+#[test]
+fn complete_readings() {
+    assert_eq!(
+        formatted(&complete_decompositions(&tiles("111222333m456p11z"))),
+        ["111m 222m 333m 456p 11z", "123m 123m 123m 456p 11z"]
+    );
+    // the nine gates won on 5m: one reading with the pair of 5m.
+    assert_eq!(
+        formatted(&complete_decompositions(&tiles("11123455678999m"))),
+        ["111m 234m 55m 678m 999m"]
+    );
+    assert_eq!(complete_decompositions(&tiles("11123455678999m")).len(), 1);
+    // seven pairs are not a reading of one pair and triads.
+    assert!(complete_decompositions(&tiles("1133m5577p99s1122z")).is_empty());
+    assert!(complete_decompositions(&tiles("123m456p789s1234z")).is_empty());
+}
+
+#[test]
+fn wait_shapes() {
+    assert_eq!(wait_shape(&Block::Sequence(1), 1), Some(Wait::Ryanmen));
+    assert_eq!(wait_shape(&Block::Sequence(1), 3), Some(Wait::Ryanmen));
+    assert_eq!(wait_shape(&Block::Sequence(1), 2), Some(Wait::Kanchan));
+    // 12m waits on 3m and 89m on 7m.
+    assert_eq!(wait_shape(&Block::Sequence(0), 2), Some(Wait::Penchan));
+    assert_eq!(wait_shape(&Block::Sequence(6), 6), Some(Wait::Penchan));
+    assert_eq!(wait_shape(&Block::Sequence(0), 0), Some(Wait::Ryanmen));
+    assert_eq!(wait_shape(&Block::Pair(27), 27), Some(Wait::Tanki));
+    assert_eq!(wait_shape(&Block::Triplet(9), 9), Some(Wait::Shanpon));
+    assert_eq!(wait_shape(&Block::Sequence(1), 5), None);
+    assert_eq!(wait_shape(&Block::Pair(0), 1), None);
+
+    assert_eq!(Block::Adjacent(3).waits(), [2, 5]);
+    assert_eq!(Block::Adjacent(0).waits(), [2]);
+    assert_eq!(Block::Adjacent(7).waits(), [6]);
+    assert_eq!(Block::Gapped(18).waits(), [19]);
+    assert_eq!(Block::Pair(33).waits(), [33]);
+    assert!(Block::Sequence(0).waits().is_empty());
+}
@@ -0,0 +1,141 @@
+use rand::SeedableRng;
+use teff::game::*;
+use teff::strategy::*;
+use teff::tile::*;
+
+// calls and kans whenever it can, to cover the calls of the simulator.  the discards right after its chi or pon must
+// not be kuikae.
+struct Caller(Ukeire, Option<(usize, usize, Meld)>);
+
+impl Strategy for Caller {
+    fn act(&mut self, view: &View, actions: &[Action]) -> Action {
+        if let Some((n_melds, tile, meld)) = self.1.take() {
+            if view.player().melds.len() > n_melds {
+                let forbidden = kuikae(tile, meld);
+                assert!(actions.iter().all(|a| match a {
+                    Action::Discard(t) => !forbidden.contains(t),
+                    _ => true,
+                }));
+            }
+        }
+        match actions
+            .iter()
+            .find(|a| matches!(a, Action::ClosedKan(_) | Action::AddedKan(_)))
+        {
+            Some(a) => *a,
+            None => self.0.act(view, actions),
+        }
+    }
+
+    fn call(&mut self, view: &View, tile: usize, _: usize, calls: &[Call]) -> Call {
+        let call = *calls.last().unwrap();
+        self.1 = match call {
+            Call::Chi(i) => Some((view.player().melds.len(), tile, Meld::Chi(i))),
+            Call::Pon => Some((view.player().melds.len(), tile, Meld::Pon(tile))),
+            _ => None,
+        };
+        call
+    }
+}
+
+fn play(strategies: &mut [&mut dyn Strategy]) {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut n_wins = 0;
+    for i in 0..8 {
+        let config = RoundConfig {
+            dealer: i % N_PLAYERS,
+            deposits: 1,
+            ..Default::default()
+        };
+        if check(&config, &play_round(&config, strategies, &mut rng)) {
+            n_wins += 1;
+        }
+    }
+    assert!(n_wins > 0);
+}
+
+// checks that the points and the tiles add up, and returns whether someone won.
+fn check(config: &RoundConfig, result: &RoundResult) -> bool {
+    let sum: isize = result.deltas.iter().sum();
+    assert_eq!(sum, 1000 * (config.deposits as isize - result.deposits as isize));
+    for p in result.players.iter() {
+        let n_tiles = p.n_concealed() + 3 * p.melds.len();
+        assert!(n_tiles == 13 || n_tiles == 14);
+    }
+    let won = matches!(result.outcome, Outcome::Tsumo { .. } | Outcome::Ron { .. });
+    if won {
+        assert_eq!(result.deposits, 0);
+    }
+    won
+}
+
+#[test]
+fn conservation() {
+    let (mut s0, mut s1, mut s2, mut s3) = (Ukeire, Ukeire, Ukeire, Ukeire);
+    play(&mut [&mut s0, &mut s1, &mut s2, &mut s3]);
+
+    let (mut s0, mut s1, mut s2, mut s3) = (Caller(Ukeire, None), Ukeire, Caller(Ukeire, None), Ukeire);
+    play(&mut [&mut s0, &mut s1, &mut s2, &mut s3]);
+}
+
+// a round of each engine against the ukeire strategy, with few samples.
+#[test]
+fn engines() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut search = Search { depth: 1 };
+    let mut playout = Playout {
+        depth: 1,
+        n_samples: 16,
+    };
+    let mut uct = Uct { n_samples: 64 };
+    let (mut s1, mut s2, mut s3) = (Ukeire, Ukeire, Ukeire);
+    for engine in [&mut search as &mut dyn Strategy, &mut playout, &mut uct] {
+        let config = RoundConfig {
+            deposits: 1,
+            ..Default::default()
+        };
+        check(
+            &config,
+            &play_round(&config, &mut [engine, &mut s1, &mut s2, &mut s3], &mut rng),
+        );
+    }
+
+    for name in ["ukeire", "search", "playout", "uct"] {
+        assert!(by_name(name).is_some());
+    }
+    assert!(by_name("foo").is_none());
+    assert!(by_name("").is_none());
+}
+
+#[test]
+fn arena() {
+    let (mut s0, mut s1, mut s2, mut s3) = (Ukeire, Ukeire, Ukeire, Ukeire);
+    let config = GameConfig {
+        n_winds: 1,
+        ..Default::default()
+    };
+    let stats = teff::arena::run(&config, &mut [&mut s0, &mut s1, &mut s2, &mut s3], 1, 0);
+
+    assert_eq!(stats.iter().map(|s| s.n_games).sum::<usize>(), N_PLAYERS);
+    assert_eq!(stats.iter().map(|s| s.rank_sum).sum::<usize>(), 6);
+    assert_eq!(
+        stats.iter().map(|s| s.score_sum).sum::<isize>(),
+        N_PLAYERS as isize * INITIAL_SCORE
+    );
+    assert!(stats.iter().map(|s| s.n_wins).sum::<usize>() >= stats.iter().map(|s| s.n_deal_ins).sum());
+}
+
+#[test]
+fn kuikae_discards() {
+    assert_eq!(kuikae(0, Meld::Chi(0)), [0, 3]);
+    assert_eq!(kuikae(3, Meld::Chi(1)), [3, 0]);
+    assert_eq!(kuikae(2, Meld::Chi(1)), [2]);
+    assert_eq!(kuikae(6, Meld::Chi(6)), [6]);
+    assert_eq!(kuikae(31, Meld::Pon(31)), [31]);
+
+    // a chi of 1m with 23m leaving only 1m and 4m, or a pon leaving only the called tile, is not allowed.
+    assert!(!can_discard_after(&parse_tile_set("2314m").unwrap(), 0, Meld::Chi(0)));
+    assert!(can_discard_after(&parse_tile_set("2315m").unwrap(), 0, Meld::Chi(0)));
+    assert!(!can_discard_after(&parse_tile_set("555z").unwrap(), 31, Meld::Pon(31)));
+    assert!(can_discard_after(&parse_tile_set("556z").unwrap(), 31, Meld::Pon(31)));
+}
@@ -0,0 +1,211 @@
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::*;
+use teff::game::*;
+use teff::mjai::*;
+use teff::strategy::*;
+
+fn start_kyoku() -> Value {
+    let hand = [
+        "1m", "2m", "3m", "4p", "5pr", "6p", "7s", "8s", "E", "E", "E", "9p", "9p",
+    ];
+    let other = vec!["?"; 13];
+    json!({
+        "type": "start_kyoku", "bakaze": "E", "kyoku": 1, "honba": 0, "kyotaku": 0, "oya": 0,
+        "dora_marker": "1s", "tehais": [other, hand, other, other],
+    })
+}
+
+// a server which the bot of `strategy` connects to, returning the thread of the bot, and the reader and the writer of
+// the connection.
+fn serve(strategy: Box<dyn Strategy + Send>) -> (thread::JoinHandle<()>, BufReader<net::TcpStream>, net::TcpStream) {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let client = thread::spawn(move || {
+        let mut bot = Bot::new(strategy, "test");
+        connect(&mut bot, addr).unwrap();
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    (client, BufReader::new(stream.try_clone().unwrap()), stream)
+}
+
+#[test]
+fn tcp() {
+    let (client, mut reader, mut writer) = serve(Box::new(Ukeire));
+    let mut send = |event: Value| -> Value {
+        writeln!(writer, "{}", event).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        serde_json::from_str(&line).unwrap()
+    };
+    let none = json!({ "type": "none" });
+
+    assert_eq!(
+        send(json!({ "type": "hello", "protocol": "mjsonp", "protocol_version": 3 })),
+        json!({ "type": "join", "name": "test", "room": "default" })
+    );
+    assert_eq!(
+        send(json!({ "type": "start_game", "id": 1, "names": ["a", "b", "c", "d"] })),
+        none
+    );
+    assert_eq!(send(start_kyoku()), none);
+    assert_eq!(send(json!({ "type": "tsumo", "actor": 0, "pai": "?" })), none);
+    // a chi is possible but not taken.
+    assert_eq!(
+        send(json!({ "type": "dahai", "actor": 0, "pai": "3p", "tsumogiri": true })),
+        none
+    );
+    // tenpai after discarding the drawn tile.
+    assert_eq!(
+        send(json!({ "type": "tsumo", "actor": 1, "pai": "N" })),
+        json!({ "type": "reach", "actor": 1 })
+    );
+    assert_eq!(
+        send(json!({ "type": "reach", "actor": 1 })),
+        json!({ "type": "dahai", "actor": 1, "pai": "N", "tsumogiri": true })
+    );
+    assert_eq!(
+        send(json!({ "type": "dahai", "actor": 1, "pai": "N", "tsumogiri": true })),
+        none
+    );
+    assert_eq!(
+        send(
+            json!({ "type": "reach_accepted", "actor": 1, "deltas": [0, -1000, 0, 0], "scores": [25000, 24000, 25000, 25000] })
+        ),
+        none
+    );
+    assert_eq!(send(json!({ "type": "tsumo", "actor": 2, "pai": "?" })), none);
+    assert_eq!(
+        send(json!({ "type": "dahai", "actor": 2, "pai": "9s", "tsumogiri": true })),
+        json!({ "type": "hora", "actor": 1, "target": 2, "pai": "9s" })
+    );
+    writeln!(writer, "{}", json!({ "type": "end_game" })).unwrap();
+    client.join().unwrap();
+}
+
+#[test]
+fn stdio() {
+    let events = [
+        json!({ "type": "hello" }),
+        json!({ "type": "start_game", "id": 1 }),
+        start_kyoku(),
+        json!({ "type": "tsumo", "actor": 1, "pai": "5p" }),
+        json!({ "type": "reach", "actor": 1 }),
+        json!({ "type": "end_game" }),
+    ];
+    let input: String = events.iter().map(|e| format!("{}\n", e)).collect();
+    let mut output = Vec::new();
+    let mut bot = Bot::new(by_name("search").unwrap(), "test");
+    run(&mut bot, io::Cursor::new(input), &mut output).unwrap();
+
+    let responses: Vec<Value> = output
+        .lines()
+        .map(|l| serde_json::from_str(&l.unwrap()).unwrap())
+        .collect();
+    assert_eq!(responses.len(), 5);
+    assert_eq!(responses[3], json!({ "type": "reach", "actor": 1 }));
+    // one of the fives is discarded, keeping the red one.
+    assert_eq!(
+        responses[4],
+        json!({ "type": "dahai", "actor": 1, "pai": "5p", "tsumogiri": true })
+    );
+}
+
+// takes the first legal action and never calls.
+struct First;
+
+impl Strategy for First {
+    fn act(&mut self, _: &View, actions: &[Action]) -> Action {
+        actions[0]
+    }
+
+    fn call(&mut self, _: &View, _: usize, _: usize, _: &[Call]) -> Call {
+        Call::Pass
+    }
+}
+
+#[test]
+fn kuikae() {
+    let mut bot = Bot::new(Box::new(First), "test");
+    let hand = [
+        "2m", "3m", "4m", "5m", "4p", "5p", "6p", "7s", "8s", "9s", "E", "E", "E",
+    ];
+    let mut start = start_kyoku();
+    start["tehais"][1] = json!(hand);
+    bot.respond(&json!({ "type": "start_game", "id": 1 })).unwrap();
+    bot.respond(&start).unwrap();
+    bot.respond(&json!({ "type": "tsumo", "actor": 0, "pai": "?" }))
+        .unwrap();
+    bot.respond(&json!({ "type": "dahai", "actor": 0, "pai": "1m", "tsumogiri": true }))
+        .unwrap();
+    // after a chi of 1m with 23m, neither 1m nor 4m may be discarded.
+    let response = bot
+        .respond(&json!({ "type": "chi", "actor": 1, "target": 0, "pai": "1m", "consumed": ["2m", "3m"] }))
+        .unwrap();
+    assert_eq!(
+        response,
+        Some(json!({ "type": "dahai", "actor": 1, "pai": "5m", "tsumogiri": false }))
+    );
+}
+
+// a discard of a tile we do not hold means that the events do not match the round.
+#[test]
+fn inconsistent_events() {
+    let events = [
+        json!({ "type": "start_game", "id": 1 }),
+        start_kyoku(),
+        json!({ "type": "dahai", "actor": 1, "pai": "1s", "tsumogiri": false }),
+    ];
+    let input: String = events.iter().map(|e| format!("{}\n", e)).collect();
+    let mut bot = Bot::new(Box::new(First), "test");
+    let e = run(&mut bot, io::Cursor::new(input), io::sink()).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(e.to_string(), "no 1s to remove");
+}
+
+// takes the first legal action and pons whenever it can.
+struct Pon;
+
+impl Strategy for Pon {
+    fn act(&mut self, _: &View, actions: &[Action]) -> Action {
+        actions[0]
+    }
+
+    fn call(&mut self, _: &View, _: usize, _: usize, calls: &[Call]) -> Call {
+        if calls.contains(&Call::Pon) {
+            Call::Pon
+        } else {
+            Call::Pass
+        }
+    }
+}
+
+// the called tile is named as discarded, keeping a red five.
+#[test]
+fn pon_red_five() {
+    let (client, mut reader, mut writer) = serve(Box::new(Pon));
+    let hand = [
+        "1m", "2m", "3m", "5p", "5p", "7s", "8s", "9s", "E", "E", "E", "9p", "9p",
+    ];
+    let mut start = start_kyoku();
+    start["tehais"][1] = json!(hand);
+    let events = [
+        json!({ "type": "start_game", "id": 1 }),
+        start,
+        json!({ "type": "tsumo", "actor": 0, "pai": "?" }),
+        json!({ "type": "dahai", "actor": 0, "pai": "5pr", "tsumogiri": true }),
+    ];
+    let mut line = String::new();
+    for event in events.iter() {
+        writeln!(writer, "{}", event).unwrap();
+        line.clear();
+        reader.read_line(&mut line).unwrap();
+    }
+    assert_eq!(
+        serde_json::from_str::<Value>(&line).unwrap(),
+        json!({ "type": "pon", "actor": 1, "target": 0, "pai": "5pr", "consumed": ["5p", "5p"] })
+    );
+    writeln!(writer, "{}", json!({ "type": "end_game" })).unwrap();
+    client.join().unwrap();
+}
@@ -0,0 +1,106 @@
+// (c) Yasuhiro Fujii <http://mimosa-pudica.net>, under MIT License.
+use rand::SeedableRng;
+use teff::notation::*;
+use teff::tile::*;
+
+fn random_hands() -> Vec<TileSet> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut acc = Vec::new();
+    for i in 0..N_KINDS {
+        for _ in 0..N_COPIES {
+            acc.push(i);
+        }
+    }
+    let mut hands = Vec::new();
+    for n in [0, 1, 13, 14, 40, 136].iter() {
+        for _ in 0..16 {
+            rand::seq::SliceRandom::shuffle(&mut acc[..], &mut rng);
+            let mut hand = TileSet::new();
+            for i in acc[..*n].iter() {
+                hand.add(*i).unwrap();
+            }
+            hands.push(hand);
+        }
+    }
+    hands
+}
+
+#[test]
+fn round_trip() {
+    for hand in random_hands() {
+        let text = format_tile_set(&hand);
+        assert_eq!(parse_tile_set(&text).unwrap(), hand);
+
+        let ids = format_tenhou136(&hand, &TileSet::new());
+        assert_eq!(format_tile_set(&parse_tenhou136(&ids).unwrap()), text);
+
+        let mjai = format_mjai_tile_set(&hand);
+        assert_eq!(format_tile_set(&parse_mjai_tile_set(&mjai).unwrap()), text);
+
+        let unicode = format_unicode_tile_set(&hand);
+        assert_eq!(format_tile_set(&parse_unicode_tile_set(&unicode).unwrap()), text);
+
+        let letters = format_letter_tile_set(&hand);
+        assert_eq!(format_tile_set(&parse_letter_tile_set(&letters).unwrap()), text);
+    }
+}
+
+// a plain five keeps off copy 0, the red one, through a round trip.
+#[test]
+fn plain_five() {
+    let hand = parse_tile_set("5p").unwrap();
+    let ids = format_tenhou136(&hand, &TileSet::new());
+    assert_eq!(ids, [53]);
+    assert_eq!(format_tenhou136(&parse_tenhou136(&ids).unwrap(), &TileSet::new()), ids);
+    assert_eq!(format_tenhou136(&hand, &hand), [52]);
+}
+
+#[test]
+fn known_notations() {
+    let hand = parse_tile_set("155m1p9s1567z").unwrap();
+    assert_eq!(
+        parse_tenhou136(&[0, 16, 17, 36, 104, 108, 124, 128, 132]).unwrap(),
+        hand
+    );
+    assert_eq!(parse_mjai_tile_set("1m 5mr 5m 1p 9s E P F C").unwrap(), hand);
+    // a five is plain unless it is given as red.
+    assert_eq!(
+        format_tenhou136(&hand, &TileSet::new()),
+        [0, 17, 18, 36, 104, 108, 124, 128, 132]
+    );
+    assert_eq!(
+        format_tenhou136(&hand, &parse_tile_set("5m").unwrap()),
+        [0, 16, 17, 36, 104, 108, 124, 128, 132]
+    );
+    assert_eq!(parse_unicode_tile_set("🀇🀋🀋🀙🀘🀀🀆🀅🀄\u{fe0f}").unwrap(), hand);
+    assert_eq!(parse_letter_tile_set("155m1p9sEPFC").unwrap(), hand);
+    assert_eq!(format_letter_tile_set(&hand), "155m 1p 9s EPFC ");
+    assert_eq!(format_mjai_tile_set(&hand), "1m 5m 5m 1p 9s E P F C");
+}
+
+#[test]
+fn errors() {
+    let error = |pos, kind| Err(ParseError { pos, kind });
+    assert_eq!(parse_tenhou136(&[0, 136]), error(1, ParseErrorKind::UnknownTile));
+    // the same physical tile cannot be held twice, and so no fifth copy either.
+    assert_eq!(
+        parse_tenhou136(&[0, 1, 2, 3, 3]),
+        error(4, ParseErrorKind::DuplicateId(3))
+    );
+    assert_eq!(
+        parse_tenhou136(&[16, 17, 16]),
+        error(2, ParseErrorKind::DuplicateId(16))
+    );
+    assert_eq!(parse_mjai_tile_set("1m 0m"), error(3, ParseErrorKind::UnknownTile));
+    // only the fives can be red.
+    assert_eq!(parse_mjai_tile_set("5mr 1mr"), error(4, ParseErrorKind::UnknownTile));
+    assert_eq!(parse_mjai_tile("Er"), None);
+    assert_eq!(
+        parse_mjai_tile_set("E E E E E"),
+        error(8, ParseErrorKind::TooManyCopies(27))
+    );
+    assert_eq!(parse_unicode_tile_set("🀇x"), error(1, ParseErrorKind::UnknownTile));
+    assert_eq!(parse_letter_tile_set("12E"), error(0, ParseErrorKind::DanglingDigits));
+    assert_eq!(parse_letter_tile_set("1mX"), error(2, ParseErrorKind::UnknownSuit('X')));
+    assert_eq!(parse_tile_set("1m9z"), error(2, ParseErrorKind::InvalidHonor(9)));
+}
@@ -0,0 +1,34 @@
+use teff::playout::*;
+use teff::score::*;
+use teff::tile::*;
+
+// 6789m 456p 234678s 55s won on 7m: 678m makes tanyao, while 789m has no yaku.
+#[test]
+fn best_of_subsets() {
+    let hand = parse_tile_set("6789m456p234678s55s").unwrap();
+    let subsets = winning_subsets(&hand, 6, 14);
+    assert_eq!(subsets.len(), 2);
+    assert!(subsets.iter().all(|s| s.count() == 14 && s.is_subset(&hand)));
+
+    let s = best_score(&hand, &[], 6, 14, &WinContext::new(WinType::Ron)).unwrap();
+    assert!(s.yaku.iter().any(|(y, _)| *y == Yaku::Tanyao));
+    // a subset must contain the winning tile.
+    assert!(winning_subsets(&hand, 5, 14).iter().all(|s| s.tile(5) > 0));
+}
+// 234m 678s 345p 5s 9m with a pon of 5p: discarding 9m waits on 5s with tanyao, while the 9m wait has no yaku.
+// 234m 678s 345p 5s 9m with a pon of 5p: both tanki waits are alike, but only the 5s wait keeps tanyao.
+#[test]
+fn discard_value() {
+    let mut hand = parse_tile_set("234m678s345p5s9m").unwrap();
+    let melds = [Meld::Pon(13)];
+    let mut wall = remaining_tiles(&[&hand, &melds[0].tiles()]).unwrap();
+    let ctx = WinContext::new(WinType::Tsumo);
+    let values = discard_tile_value_parallel(&mut hand, &melds, &mut wall, &Horizon::new(5), &ctx, 1 << 13, 0);
+    assert_eq!(values.len(), 11);
+    let value = |tile| values.iter().find(|(t, _, _)| *t == tile).unwrap();
+
+    let (best, _, _) = values.iter().max_by(|a, b| a.2.partial_cmp(&b.2).unwrap()).unwrap();
+    assert_eq!(*best, 8);
+    assert!(value(8).1 > 0.25);
+    assert!(value(8).2 > 1.5 * value(22).2);
+}
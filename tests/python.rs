@@ -0,0 +1,25 @@
+#![cfg(feature = "python")]
+use std::path::PathBuf;
+use std::process::Command;
+
+// runs tests/python/teff_test.py against the cdylib built for the tests, which is next to the test executable, renamed
+// to what Python imports.
+#[test]
+#[cfg_attr(not(has_python), ignore = "no Python")]
+fn python_module() {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let exe = std::env::current_exe().unwrap();
+    let lib_dir = exe.parent().unwrap();
+    let module_dir = lib_dir.join("python");
+    std::fs::create_dir_all(&module_dir).unwrap();
+    let lib = format!("{}teff{}", std::env::consts::DLL_PREFIX, std::env::consts::DLL_SUFFIX);
+    let module = if cfg!(windows) { "teff.pyd" } else { "teff.so" };
+    std::fs::copy(lib_dir.join(lib), module_dir.join(module)).unwrap();
+    let output = Command::new("python3")
+        .arg(root.join("tests/python/teff_test.py"))
+        .arg(&module_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, b"ok\n");
+}
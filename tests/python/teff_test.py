@@ -0,0 +1,31 @@
+# imports the module built with `--features python` from the directory given as the argument.
+import sys
+sys.path.insert(0, sys.argv[1])
+import teff
+
+hand = teff.parse_tile_set("123m456p789s1122z3z")
+assert str(hand) == "123m 456p 789s 11223z"
+assert teff.TileSet(list(hand)) == hand
+assert len(hand) == teff.N_KINDS and hand.count() == 14
+assert hand[27] == 2 and hand.counts()[29] == 1
+try:
+    teff.parse_tile_set("12x")
+    assert False
+except ValueError:
+    pass
+
+assert teff.shanten(hand) == 0
+wait = hand.copy()
+wait[29] = 0
+counts = teff.ukeire(wait)
+assert len(counts) == teff.N_KINDS
+assert counts[27] == 2 and counts[28] == 2 and sum(counts) == 4
+seen = teff.TileSet()
+seen[27] = 2
+assert teff.ukeire(wait, seen)[27] == 0
+
+assert teff.search(hand, depth=1)[0][0] == 29
+assert teff.playout(hand, depth=1, n_samples=1 << 10)[0][0] == 29
+tile, riichi, turns, points, count = teff.uct(hand, n_samples=1 << 10, seed=1)[0]
+assert tile == 29 and count > 0
+print("ok")
@@ -0,0 +1,42 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use teff::playout::Horizon;
+use teff::score::*;
+use teff::tile::*;
+use teff::uct;
+
+#[test]
+fn commands() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_teff"))
+        .args(["repl", "--samples", "4096", "123m456p789s11223z"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input = "discard 3z\ndraw 9m\nsee 1z 1z\nundo\ndraw 1m\nfoo\nquit\n";
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let text = String::from_utf8(output.stdout).unwrap();
+
+    assert!(text.contains("by UCT (* = riichi), n_samples = 4096:"));
+    assert!(text.contains("Hand: 123m 456p 789s 1122z \nVisible: 3z \n  shanten = 0, ukeire = 4: 1z 2z\n"));
+    assert!(text.contains("Hand: 1239m 456p 789s 1122z \n"));
+    assert!(text.contains("Visible: 113z \n"));
+    assert!(text.contains("# of tiles must be 3n + 1 to draw."));
+    assert!(text.contains("Unknown command or argument: foo."));
+}
+
+#[test]
+fn tree() {
+    let hand = parse_tile_set("123m456p789s11223z").unwrap();
+    let wall = remaining_tiles(&[&hand]).unwrap();
+    let ctx = WinContext::new(WinType::Tsumo);
+    let mut tree = uct::Tree::new(&hand, &[], &wall, &Horizon::new(15), &ctx);
+    tree.sample(1 << 14, &mut rand::thread_rng());
+    assert_eq!(tree.n_samples(), 1 << 14);
+
+    // the samples after discarding 3z and drawing 5m are kept.
+    tree.advance(29, 4);
+    assert!(tree.n_samples() > 0);
+    assert!(tree.actions().iter().all(|a| a.count > 0));
+}
@@ -0,0 +1,205 @@
+use std::*;
+use teff::replay::*;
+use teff::shanten;
+use teff::tile::*;
+
+fn fixture(name: &str) -> String {
+    fs::read_to_string(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+fn tiles(text: &str) -> TileSet {
+    parse_tile_set(text).unwrap()
+}
+
+#[test]
+fn formats() {
+    let mjlog = parse(&fixture("round.mjlog")).unwrap();
+    let tenhou6 = parse(&fixture("round.json")).unwrap();
+    assert_eq!(mjlog, tenhou6);
+
+    assert_eq!(mjlog.names, ["あ", "B", "C", "D"]);
+    let round = &mjlog.rounds[0];
+    assert_eq!(
+        (round.round_wind, round.dealer, round.honba, round.deposits),
+        (0, 0, 0, 0)
+    );
+    assert_eq!(round.scores, [25000; 4]);
+    assert_eq!(round.dora_indicator, 18);
+    assert_eq!(round.hands[0], tiles("123m456p789s1123z"));
+    assert_eq!(round.events.len(), 15);
+    assert_eq!(
+        round.events[1],
+        Event::Discard {
+            seat: 0,
+            tile: 28,
+            riichi: true,
+            tsumogiri: false
+        }
+    );
+    assert_eq!(
+        round.events[8],
+        Event::Call {
+            seat: 1,
+            from: 3,
+            meld: Meld::Pon(31)
+        }
+    );
+    assert_eq!(round.events[14], Event::Win { seat: 0, from: 3 });
+}
+
+#[test]
+fn positions() {
+    let replay = parse(&fixture("round.json")).unwrap();
+    let positions = replay.positions(1).unwrap();
+    assert_eq!(positions.len(), 2);
+
+    let p = &positions[0];
+    assert_eq!(p.hand, tiles("1145m9m123p34s5577z"));
+    assert_eq!((p.discard, p.riichi), (8, false));
+    assert!(p.table.opponents[2].riichi);
+    assert_eq!(p.table.opponents[2].discards, tiles("2z"));
+    assert_eq!(p.table.opponent_tiles, 39);
+    assert_eq!(p.table.seat_wind, 1);
+
+    // after the pon, the called haku is in the meld but not in the pond of toimen.
+    let p = &positions[1];
+    assert_eq!(p.hand, tiles("1145m123p34s77z"));
+    assert_eq!(p.melds, [Meld::Pon(31)]);
+    assert_eq!(p.table.own_discards, tiles("9m"));
+    assert_eq!(p.table.opponents[1].discards, TileSet::new());
+    assert_eq!(p.table.melds, tiles("555z"));
+    assert_eq!(p.table.wall(&p.hand).unwrap().count(), 136 - 11 - 7);
+
+    // the riichi discard left the hand in tenpai.
+    let p = &replay.positions(0).unwrap()[0];
+    let mut hand = p.hand.clone();
+    hand.remove(p.discard).unwrap();
+    assert_eq!(shanten::shanten(&hand), 0);
+}
+
+#[test]
+fn errors() {
+    let text = fixture("round.json");
+    assert!(parse(&text[..text.len() / 2]).is_err());
+    assert_eq!(
+        parse(&text.replace("[39, 22]", "[39, 99]")),
+        Err(ReplayError::InvalidTile(99))
+    );
+    assert!(parse_mjlog("<mjloggm><T0/></mjloggm>").is_err());
+
+    let text = fixture("round.mjlog");
+    for oya in ["4", "-1"].iter() {
+        let init = format!("oya=\"{}\" hai0", oya);
+        assert_eq!(
+            parse(&text.replace("oya=\"0\" hai0", &init)),
+            Err(ReplayError::Syntax("INIT".to_string()))
+        );
+    }
+    // a pon and an added kan of the 43rd kind, and a chi based on the 22nd run of three.
+    for m in [126 << 9 | 0x8, 126 << 9 | 0x10, 63 << 10 | 0x4].iter() {
+        assert_eq!(
+            parse(&text.replace("m=\"47626\"", &format!("m=\"{}\"", m))),
+            Err(ReplayError::InvalidMeld(*m))
+        );
+    }
+}
+
+// two rounds with every kind of call and kan, a riichi by tsumogiri ("r60"), a tsumo and a draw.
+#[test]
+fn calls_and_rounds() {
+    let mjlog = parse(&fixture("game.mjlog")).unwrap();
+    let tenhou6 = parse(&fixture("game.json")).unwrap();
+    assert_eq!(mjlog, tenhou6);
+    assert_eq!(mjlog.rounds.len(), 2);
+    for seat in 0..4 {
+        assert!(mjlog.positions(seat).is_ok());
+    }
+
+    let draw = |seat, tile| Event::Draw { seat, tile };
+    let discard = |seat, tile, tsumogiri| Event::Discard {
+        seat,
+        tile,
+        riichi: false,
+        tsumogiri,
+    };
+    let round = &mjlog.rounds[0];
+    assert_eq!(round.dora_indicator, 2);
+    assert_eq!(
+        round.events,
+        [
+            draw(0, 9),
+            discard(0, 9, true),
+            Event::Call {
+                seat: 1,
+                from: 0,
+                meld: Meld::Chi(9)
+            },
+            discard(1, 30, false),
+            Event::Call {
+                seat: 2,
+                from: 1,
+                meld: Meld::Kan(30)
+            },
+            Event::Dora(6),
+            draw(2, 17),
+            discard(2, 33, false),
+            draw(3, 8),
+            discard(3, 0, false),
+            draw(0, 27),
+            Event::ClosedKan { seat: 0, tile: 27 },
+            Event::Dora(16),
+            draw(0, 29),
+            discard(0, 29, true),
+            draw(1, 32),
+            discard(1, 32, true),
+            Event::Call {
+                seat: 3,
+                from: 1,
+                meld: Meld::Pon(32)
+            },
+            discard(3, 29, false),
+            draw(0, 28),
+            Event::Discard {
+                seat: 0,
+                tile: 28,
+                riichi: true,
+                tsumogiri: true
+            },
+            draw(1, 9),
+            discard(1, 9, true),
+            draw(2, 7),
+            discard(2, 7, true),
+            draw(3, 32),
+            Event::AddedKan { seat: 3, tile: 32 },
+            Event::Dora(19),
+            draw(3, 4),
+            discard(3, 4, true),
+            draw(0, 23),
+            Event::Win { seat: 0, from: 0 },
+        ]
+    );
+
+    // the second round is dealt by seat 1, with a red five read as an ordinary one.
+    let round = &mjlog.rounds[1];
+    assert_eq!(
+        (round.round_wind, round.dealer, round.honba, round.deposits),
+        (0, 1, 0, 0)
+    );
+    assert_eq!(round.scores, [32800, 22400, 22400, 22400]);
+    assert_eq!(round.dora_indicator, 17);
+    assert_eq!(round.hands[0], tiles("123m566p789s1234z"));
+    assert_eq!(
+        round.events,
+        [
+            draw(1, 13),
+            discard(1, 8, false),
+            draw(2, 26),
+            discard(2, 26, true),
+            draw(3, 28),
+            discard(3, 28, true),
+            draw(0, 29),
+            discard(0, 29, true),
+            Event::Ryuukyoku,
+        ]
+    );
+}
@@ -0,0 +1,53 @@
+use std::*;
+use teff::replay;
+use teff::review::*;
+
+fn fixture(name: &str) -> replay::Replay {
+    let text = fs::read_to_string(format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap();
+    replay::parse(&text).unwrap()
+}
+
+#[test]
+fn grades() {
+    let replay = fixture("round.json");
+    let engine = Engine::Search { depth: 1 };
+    let grades = review(&replay, 1, &engine, 0.0).unwrap();
+    assert_eq!(grades.len(), 2);
+    for g in grades.iter() {
+        assert_eq!(g.round, "E1-0");
+        assert_eq!(g.best, g.scores[0].0);
+        assert!(g.scores.windows(2).all(|w| w[0].1 <= w[1].1));
+        assert!(g.loss >= 0.0);
+        assert_eq!(g.flagged, g.loss > 0.0);
+    }
+    assert_eq!((grades[0].turn, grades[1].turn), (1, 2));
+    assert_eq!(grades[1].melds.len(), 1);
+
+    // nothing is flagged above the largest loss.
+    let max_loss = grades.iter().map(|g| g.loss).fold(0.0, f64::max);
+    assert!(review(&replay, 1, &engine, max_loss)
+        .unwrap()
+        .iter()
+        .all(|g| !g.flagged));
+
+    let text = format_text(&grades);
+    assert!(text.starts_with("E1-0 #1"));
+    let json = to_json(&grades, 1, &engine, 0.0);
+    assert_eq!(json["engine"], "search");
+    assert_eq!(json["decisions"].as_array().unwrap().len(), 2);
+    assert_eq!(json["decisions"][1]["melds"][0], "555z");
+}
+
+#[test]
+fn riichi() {
+    // the riichi discard is graded as an ordinary one.
+    let grades = review(
+        &fixture("round.mjlog"),
+        0,
+        &Engine::Search { depth: 1 },
+        DEFAULT_THRESHOLD,
+    )
+    .unwrap();
+    assert_eq!(grades.len(), 1);
+    assert_eq!(grades[0].discard, 28);
+}
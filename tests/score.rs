@@ -0,0 +1,158 @@
+use teff::score::*;
+use teff::tile::*;
+
+fn tile(text: &str) -> usize {
+    parse_tile_set(text).unwrap().iter().position(|n| *n > 0).unwrap()
+}
+
+// the score of the hand won on `win` by a non-dealer in the east round, with the changes of `f` to the context.
+fn score(hand: &str, melds: &[Meld], win: &str, win_type: WinType, f: impl FnOnce(&mut WinContext)) -> Option<Score> {
+    let mut ctx = WinContext::new(win_type);
+    ctx.seat_wind = 1;
+    f(&mut ctx);
+    score_hand(&parse_tile_set(hand).unwrap(), melds, tile(win), &ctx)
+}
+
+fn has(score: &Score, yaku: Yaku) -> bool {
+    score.yaku.iter().any(|(y, _)| *y == yaku)
+}
+
+// 234m 456p 55p 678s 23s + 4s: tanyao and pinfu.
+const PINFU: &str = "234m45556p234678s";
+
+#[test]
+fn pinfu() {
+    let s = score(PINFU, &[], "4s", WinType::Tsumo, |_| ()).unwrap();
+    assert!(has(&s, Yaku::Pinfu) && has(&s, Yaku::Tanyao) && has(&s, Yaku::MenzenTsumo));
+    assert_eq!((s.han, s.fu), (3, 20));
+    assert_eq!((s.points, s.payment), (2700, Payment::Tsumo(700, 1300)));
+
+    let s = score(PINFU, &[], "4s", WinType::Ron, |_| ()).unwrap();
+    assert!(has(&s, Yaku::Pinfu) && !has(&s, Yaku::MenzenTsumo));
+    assert_eq!((s.han, s.fu), (2, 30));
+    assert_eq!((s.points, s.payment), (2000, Payment::Ron(2000)));
+
+    // a kanchan wait is not pinfu.
+    let s = score("234m45556p234678s", &[], "3s", WinType::Ron, |_| ()).unwrap();
+    assert!(!has(&s, Yaku::Pinfu));
+    assert_eq!((s.han, s.fu), (1, 40));
+}
+
+#[test]
+fn chiitoitsu() {
+    let s = score("1133m5577p99s1122z", &[], "2z", WinType::Ron, |_| ()).unwrap();
+    assert!(has(&s, Yaku::Chiitoitsu));
+    assert_eq!((s.han, s.fu, s.points), (2, 25, 1600));
+
+    let s = score("1133m5577p99s1122z", &[], "2z", WinType::Tsumo, |_| ()).unwrap();
+    assert_eq!((s.han, s.fu), (3, 25));
+    assert_eq!(s.payment, Payment::Tsumo(800, 1600));
+}
+
+#[test]
+fn fu() {
+    // 20 + 10 (closed ron) + 8 (concealed 111m) = 38, rounded up to 40.
+    let s = score("111m23444p567789s", &[], "4p", WinType::Ron, |c| c.riichi = true).unwrap();
+    assert_eq!((s.han, s.fu, s.points), (1, 40, 1300));
+
+    // a ron on a shanpon wait makes an open triplet: 20 + 10 + 8 (111m) + 4 (999s) = 42, rounded up to 50.
+    let s = score("111m234p567s999s33z", &[], "9s", WinType::Ron, |c| c.riichi = true).unwrap();
+    assert_eq!(s.fu, 50);
+
+    // an open hand of sequences has 30 fu on ron.
+    let s = score("234m45556p234s", &[Meld::Chi(tile("6s"))], "4s", WinType::Ron, |_| ()).unwrap();
+    assert!(has(&s, Yaku::Tanyao) && !has(&s, Yaku::Pinfu));
+    assert_eq!((s.han, s.fu, s.points), (1, 30, 1000));
+}
+
+#[test]
+fn limits() {
+    let points = |n_red, hand: &str, win: &str, riichi: bool| {
+        let s = score(hand, &[], win, WinType::Ron, |c| {
+            c.n_red = n_red;
+            c.riichi = riichi;
+        })
+        .unwrap();
+        (s.han, s.fu, s.points)
+    };
+    // no kiriage mangan: 4 han 30 fu stays below mangan.
+    assert_eq!(points(2, PINFU, "4s", false), (4, 30, 7700));
+    assert_eq!(points(3, "111m23444p567789s", "4p", true), (4, 40, 8000));
+    assert_eq!(points(3, PINFU, "4s", false), (5, 30, 8000));
+    assert_eq!(points(4, PINFU, "4s", false), (6, 30, 12000));
+    assert_eq!(points(5, PINFU, "4s", false), (7, 30, 12000));
+    assert_eq!(points(6, PINFU, "4s", false), (8, 30, 16000));
+    assert_eq!(points(9, PINFU, "4s", false), (11, 30, 24000));
+    // kazoe yakuman.
+    assert_eq!(points(11, PINFU, "4s", false), (13, 30, 32000));
+    assert_eq!(points(20, PINFU, "4s", false), (22, 30, 32000));
+}
+
+#[test]
+fn yakuman() {
+    let s = score("11m234p555666777z", &[], "3p", WinType::Ron, |_| ()).unwrap();
+    assert_eq!(s.yaku, vec![(Yaku::Daisangen, 13)]);
+    assert_eq!((s.yakuman, s.points), (1, 32000));
+
+    let s = score("119m19p19s1234567z", &[], "1m", WinType::Tsumo, |c| c.seat_wind = 0).unwrap();
+    assert_eq!(s.yaku, vec![(Yaku::Kokushi, 13)]);
+    assert_eq!((s.points, s.payment), (48000, Payment::TsumoAll(16000)));
+
+    // suuankou and daisangen; the dora do not count.
+    let s = score("111m22p555666777z", &[], "2p", WinType::Tsumo, |c| {
+        c.dora_indicators = vec![0]
+    })
+    .unwrap();
+    assert!(has(&s, Yaku::Suuankou) && has(&s, Yaku::Daisangen));
+    assert_eq!((s.yakuman, s.dora), (2, 0));
+    assert_eq!((s.points, s.payment), (64000, Payment::Tsumo(16000, 32000)));
+}
+
+#[test]
+fn payments() {
+    let dealer = |c: &mut WinContext| c.seat_wind = 0;
+    let s = score(PINFU, &[], "4s", WinType::Tsumo, dealer).unwrap();
+    assert_eq!((s.points, s.payment), (3900, Payment::TsumoAll(1300)));
+    let s = score(PINFU, &[], "4s", WinType::Ron, dealer).unwrap();
+    assert_eq!((s.points, s.payment), (2900, Payment::Ron(2900)));
+
+    // 300 points for each honba, split among the payers of a tsumo.  the riichi deposits are not a part of the score.
+    let s = score(PINFU, &[], "4s", WinType::Ron, |c| c.honba = 2).unwrap();
+    assert_eq!(s.payment, Payment::Ron(2600));
+    let s = score(PINFU, &[], "4s", WinType::Tsumo, |c| c.honba = 2).unwrap();
+    assert_eq!((s.points, s.payment), (3300, Payment::Tsumo(900, 1500)));
+    let s = score(PINFU, &[], "4s", WinType::Tsumo, |c| {
+        c.seat_wind = 0;
+        c.honba = 1;
+    })
+    .unwrap();
+    assert_eq!(s.payment, Payment::TsumoAll(1400));
+}
+
+#[test]
+fn dora() {
+    let with = |riichi: bool| {
+        score(PINFU, &[], "4s", WinType::Ron, |c| {
+            // 5p, three in the hand.
+            c.dora_indicators = vec![tile("4p")];
+            // 2m, one in the hand.
+            c.ura_indicators = vec![tile("1m")];
+            c.n_red = 1;
+            c.riichi = riichi;
+        })
+        .unwrap()
+    };
+    let s = with(false);
+    assert_eq!((s.dora, s.han), (4, 6));
+    // the ura dora count only with riichi.
+    let s = with(true);
+    assert_eq!((s.dora, s.han), (5, 8));
+
+    // the indicators wrap around in each suit, the winds and the dragons.
+    assert_eq!(dora_from_indicator(tile("9m")), tile("1m"));
+    assert_eq!(dora_from_indicator(tile("4z")), tile("1z"));
+    assert_eq!(dora_from_indicator(tile("7z")), tile("5z"));
+
+    // dora alone are not a yaku.
+    assert!(score("111m23444p567789s", &[], "4p", WinType::Ron, |c| c.n_red = 1).is_none());
+}
@@ -0,0 +1,108 @@
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::{thread, time};
+use teff::server;
+
+fn start(config: server::Config) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || server::serve(listener, &config).unwrap());
+    addr
+}
+
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut text = String::new();
+    stream.read_to_string(&mut text).unwrap();
+    let status = text[9..12].parse().unwrap();
+    let (_, body) = text.split_once("\r\n\r\n").unwrap();
+    (status, serde_json::from_str(body).unwrap())
+}
+
+#[test]
+fn endpoints() {
+    let addr = start(server::Config::default());
+
+    let (status, v) = request(addr, "GET", "/shanten?hand=123m456p789s1122z&discards=1z", "");
+    assert_eq!(status, 200);
+    assert_eq!(
+        v,
+        json!({"hand": "123m 456p 789s 1122z", "shanten": 0, "ukeire": 3, "tiles": ["1z", "2z"]})
+    );
+
+    let (status, v) = request(addr, "GET", "/shanten?hand=123m456p789s11223z", "");
+    assert_eq!(status, 200);
+    assert_eq!(v["discards"].as_array().unwrap().len(), 12);
+
+    let body = json!({"hand": "123m456p789s11223z", "toimen": "1m", "riichi": [2], "engine": "search", "depth": 1});
+    let (status, v) = request(addr, "POST", "/analyze", &body.to_string());
+    assert_eq!(status, 200);
+    assert_eq!(v["n_unseen"], 121);
+    let engines: Vec<_> = v["sections"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["engine"].clone())
+        .collect();
+    assert_eq!(engines, [json!("search"), json!("search"), json!("danger")]);
+    assert_eq!(v["sections"][1]["choices"][0]["tile"], "3z");
+
+    let (status, v) = request(addr, "POST", "/analyze", "{\"hand\": \"123m\"}");
+    assert_eq!(status, 400);
+    assert!(v["error"].as_str().unwrap().contains("3n + 1"));
+    assert_eq!(request(addr, "POST", "/analyze", "{").0, 400);
+    assert_eq!(
+        request(addr, "POST", "/analyze", "{\"hand\": \"1m\", \"depth\": 9}").0,
+        400
+    );
+    assert_eq!(request(addr, "GET", "/analyze", "").0, 405);
+    assert_eq!(request(addr, "GET", "/", "").0, 404);
+}
+
+#[test]
+fn time_limit() {
+    let addr = start(server::Config {
+        n_workers: 1,
+        time_limit: time::Duration::from_millis(10),
+        ..server::Config::default()
+    });
+    let body = json!({"hand": "123m456p789s11223z", "engine": "playout", "n_samples": 1 << 10});
+    let (status, v) = request(addr, "POST", "/analyze", &body.to_string());
+    assert_eq!(status, 503);
+    assert_eq!(v["error"], "Time limit exceeded");
+    // the worker is back after the analysis.
+    assert_eq!(request(addr, "GET", "/shanten?hand=1m", "").0, 200);
+}
+
+// a request which crashed the engine used to take the server down with it.
+#[test]
+fn survives_engine_errors() {
+    let addr = start(server::Config::default());
+    let body = json!({"hand": "234m456p678s3445s9m", "dora": "12z", "riichi": [1, 2, 3], "engine": "uct",
+        "n_samples": 1 << 12, "dead_wall": 78});
+    for _ in 0..4 {
+        assert_eq!(request(addr, "POST", "/analyze", &body.to_string()).0, 200);
+    }
+    assert_eq!(request(addr, "GET", "/shanten?hand=1m", "").0, 200);
+}
+
+#[test]
+fn too_large_header() {
+    let addr = start(server::Config::default());
+    let mut stream = TcpStream::connect(addr).unwrap();
+    // a line without a newline is not read to the end.
+    let _ = stream.write_all(format!("GET /{}", "a".repeat(16 << 10)).as_bytes());
+    let mut text = String::new();
+    let _ = stream.read_to_string(&mut text);
+    assert!(text.starts_with("HTTP/1.1 413 "));
+}
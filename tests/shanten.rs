@@ -0,0 +1,83 @@
+use teff::bench::*;
+use teff::shanten::*;
+use teff::table::*;
+use teff::tile::*;
+
+fn tiles(text: &str) -> TileSet {
+    parse_tile_set(text).unwrap()
+}
+
+fn full_wall(hand: &TileSet) -> TileSet {
+    TableState::new().wall(hand).unwrap()
+}
+
+#[test]
+fn shanten_numbers() {
+    assert_eq!(shanten(&tiles("123m456p789s11122z")), -1);
+    assert_eq!(shanten(&tiles("123m456p789s1122z")), 0);
+    assert_eq!(shanten(&tiles("123m456p789s11223z")), 0);
+    assert_eq!(shanten(&tiles("13579m13579p135s")), 4);
+    // seven pairs and thirteen orphans.
+    assert_eq!(shanten(&tiles("1133m5577p99s112z")), 0);
+    assert_eq!(shanten_chiitoitsu(&tiles("1133m5577p99s112z")), 0);
+    assert_eq!(shanten(&tiles("19m19p19s1234567z")), 0);
+    assert_eq!(shanten_kokushi(&tiles("19m19p19s1234567z")), 0);
+    assert_eq!(shanten_kokushi(&tiles("123m456p789s1122z")), 8);
+}
+
+#[test]
+fn ukeire_counts() {
+    let hand = tiles("123m456p789s1122z");
+    assert_eq!(ukeire(&hand, &full_wall(&hand)), [(27, 2), (28, 2)]);
+
+    let hand = tiles("123m456p23789s77z");
+    assert_eq!(ukeire(&hand, &full_wall(&hand)), [(18, 4), (21, 4)]);
+    // the tiles which are not left in the wall are not counted.
+    let mut table = TableState::new();
+    table.discards = tiles("1111s");
+    assert_eq!(ukeire(&hand, &table.wall(&hand).unwrap()), [(21, 4)]);
+}
+
+#[test]
+fn draw_analysis() {
+    let params = Params {
+        depth: 2,
+        engine: Some("search".to_string()),
+        ..Params::default()
+    };
+    let mut hand = tiles("123m456p789s1122z");
+    let a = analyze_hand(
+        &mut hand,
+        &TableState::new(),
+        &params,
+        Format::Json,
+        &mut rand::thread_rng(),
+    )
+    .unwrap();
+    let names: Vec<_> = a.sections.iter().map(|s| (s.engine, s.depth)).collect();
+    assert_eq!(
+        names,
+        [
+            ("ukeire", None),
+            ("search", Some(0)),
+            ("search", Some(1)),
+            ("waits", None)
+        ]
+    );
+    let values: Vec<_> = a.sections[0].choices[0].estimates.iter().map(|e| e.value).collect();
+    assert_eq!(values, [0.0, 4.0]);
+    for s in a.sections[1..3].iter() {
+        assert!(s.choices[0].estimates[0].value > 0.0);
+    }
+
+    // the dealer wins on 1z with double east, and on 2z only by tsumo.
+    let waits: Vec<_> = a.sections[3]
+        .choices
+        .iter()
+        .map(|c| (c.tile, c.estimates.iter().map(|e| e.value).collect::<Vec<_>>()))
+        .collect();
+    assert_eq!(waits[0], (Some(27), vec![2.0, 6000.0, 3900.0]));
+    assert_eq!(waits[1].0, Some(28));
+    assert_eq!(waits[1].1[..2], [2.0, 2100.0]);
+    assert!(waits[1].1[2].is_nan());
+}
@@ -0,0 +1,69 @@
+use teff::table::*;
+use teff::tile::*;
+
+fn tiles(text: &str) -> TileSet {
+    parse_tile_set(text).unwrap()
+}
+
+#[test]
+fn wall() {
+    let hand = tiles("123m456p789s1122z");
+    let mut table = TableState::new();
+    assert_eq!(table.wall(&hand).map(|w| w.count()), Ok(4 * N_KINDS - 13));
+
+    table.own_discards = tiles("9m");
+    table.discards = tiles("1m");
+    table.opponents[0].discards = tiles("5p");
+    table.opponents[2].discards = tiles("5p");
+    table.melds = tiles("666s");
+    table.dora_indicators = tiles("7z");
+    let wall = table.wall(&hand).unwrap();
+    assert_eq!(wall.count(), 4 * N_KINDS - 13 - 8);
+    assert_eq!(
+        (wall.tile(0), wall.tile(8), wall.tile(13), wall.tile(23), wall.tile(33)),
+        (2, 3, 1, 1, 3)
+    );
+}
+
+// the hand and the visible tiles may not have more than four copies together.
+#[test]
+fn impossible_hands() {
+    let mut table = TableState::new();
+    table.discards = tiles("11z");
+    assert!(table.wall(&tiles("123m456p789s1122z")).is_ok());
+    assert_eq!(
+        table.wall(&tiles("123m456p789s111z")).map(|w| w.count()),
+        Err(TileError::TooManyCopies(27))
+    );
+    table.opponents[1].discards = tiles("2z");
+    table.melds = tiles("22z");
+    assert_eq!(
+        table.wall(&tiles("123m456p789s1122z")),
+        Err(TileError::TooManyCopies(28))
+    );
+}
+
+#[test]
+fn n_draws() {
+    let hand = tiles("123m456p789s1122z");
+    let mut table = TableState::new();
+    // 70 live tiles at the start, shared by four players.
+    let wall = table.wall(&hand).unwrap();
+    assert_eq!(table.n_live(&wall), 70);
+    assert_eq!(table.n_draws(&wall), 17);
+
+    // a dora indicator comes from the dead wall, not the live wall.
+    table.dora_indicators = tiles("7z");
+    let wall = table.wall(&hand).unwrap();
+    assert_eq!(table.n_live(&wall), 70);
+
+    // eight discards are eight draws, two of ours.
+    table.discards = tiles("11223344m");
+    let wall = table.wall(&hand).unwrap();
+    assert_eq!(table.n_live(&wall), 62);
+    assert_eq!(table.n_draws(&wall), 15);
+
+    // never below zero.
+    table.opponent_tiles = 4 * N_KINDS;
+    assert_eq!(table.n_draws(&wall), 0);
+}
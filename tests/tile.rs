@@ -0,0 +1,68 @@
+use teff::tile::*;
+
+fn tiles(text: &str) -> TileSet {
+    parse_tile_set(text).unwrap()
+}
+
+#[test]
+fn add_and_remove() {
+    let mut set = tiles("111m");
+    assert_eq!(set.add(0), Ok(()));
+    assert_eq!(set.add(0), Err(TileError::TooManyCopies(0)));
+    assert_eq!(set.tile(0), 4);
+    assert_eq!(set.remove(33), Err(TileError::NotPresent(33)));
+    for _ in 0..4 {
+        set.remove(0).unwrap();
+    }
+    assert!(set.is_empty());
+    assert_eq!(set.remove(0), Err(TileError::NotPresent(0)));
+}
+
+#[test]
+fn set_operations() {
+    let a = tiles("1122m");
+    assert_eq!(a.union(&tiles("2m5z")), Ok(tiles("11222m5z")));
+    assert_eq!(a.union(&tiles("111m")), Err(TileError::TooManyCopies(0)));
+    assert_eq!(a.difference(&tiles("12m")), Ok(tiles("12m")));
+    assert_eq!(a.difference(&tiles("3m")), Err(TileError::NotPresent(2)));
+    assert!(tiles("12m").is_subset(&a));
+    assert!(!tiles("111m").is_subset(&a));
+
+    let mut b = TileSet::new();
+    *b.tile_mut(27) = 5;
+    assert_eq!(b.validate(), Err(TileError::TooManyCopies(27)));
+    assert_eq!(a.validate(), Ok(()));
+}
+
+#[test]
+fn remaining() {
+    let wall = remaining_tiles(&[&tiles("1122m"), &tiles("1m7z")]).unwrap();
+    assert_eq!(wall.count(), 4 * N_KINDS - 6);
+    assert_eq!((wall.tile(0), wall.tile(1), wall.tile(33)), (1, 2, 3));
+    assert_eq!(
+        remaining_tiles(&[&tiles("111m"), &tiles("11m")]),
+        Err(TileError::TooManyCopies(0))
+    );
+    assert_eq!(TileError::TooManyCopies(0).to_string(), "more than 4 copies of 1m");
+    assert_eq!(TileError::NotPresent(27).to_string(), "no 1z to remove");
+}
+
+#[test]
+fn parse_errors() {
+    let error = |pos, kind| Err(ParseError { pos, kind });
+    assert_eq!(parse_tile_set("123m 0m"), error(5, ParseErrorKind::RedFive));
+    assert_eq!(parse_tile_set("123"), error(0, ParseErrorKind::DanglingDigits));
+    assert_eq!(parse_tile_set("12m3"), error(3, ParseErrorKind::DanglingDigits));
+    assert_eq!(parse_tile_set("123x"), error(3, ParseErrorKind::UnknownSuit('x')));
+    // the fifth copy is reported at its own digit.
+    assert_eq!(parse_tile_set("11111m"), error(4, ParseErrorKind::TooManyCopies(0)));
+    assert_eq!(parse_tile_set("1m 18z"), error(4, ParseErrorKind::InvalidHonor(8)));
+    assert_eq!(
+        ParseError {
+            pos: 4,
+            kind: ParseErrorKind::TooManyCopies(0)
+        }
+        .to_string(),
+        "more than 4 copies of 1m at position 4"
+    );
+}
@@ -0,0 +1,87 @@
+use rand::SeedableRng;
+use teff::playout::{self, Horizon};
+use teff::score::*;
+use teff::tile::*;
+use teff::uct;
+
+// the round may end at any draw, and the ura dora indicators must not be taken from past its end.
+#[test]
+fn round_end_with_ura() {
+    let hand = parse_tile_set("234m456p678s3445s9m").unwrap();
+    let wall = remaining_tiles(&[&hand]).unwrap();
+    let mut ctx = WinContext::new(WinType::Tsumo);
+    ctx.dora_indicators = vec![27, 28];
+    for seed in 0..16 {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let horizon = Horizon {
+            n_draws: 3,
+            end_rate: 0.5,
+        };
+        let actions = uct::discard_tile(&hand, &[], &wall, &horizon, &ctx, 1 << 10, &mut rng);
+        assert!(actions.iter().any(|a| a.riichi));
+    }
+}
+
+// UCT and the playouts draw the same # of tiles, and a hand which cannot win within them takes them all and the
+// discard.
+#[test]
+fn same_draws() {
+    let hand = parse_tile_set("147m147p147s12345z").unwrap();
+    let mut wall = remaining_tiles(&[&hand]).unwrap();
+    let ctx = WinContext::new(WinType::Tsumo);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let actions = uct::discard_tile(&hand, &[], &wall, &Horizon::new(5), &ctx, 1 << 10, &mut rng);
+    let (_, discards) = playout::discard_tile_parallel(&mut hand.clone(), &mut wall, 5, 0, 1 << 10, 0);
+    assert_eq!(actions.len(), discards.len());
+    for (a, (tile, turns)) in actions.iter().zip(discards.iter()) {
+        assert_eq!((a.tile, a.turns), (*tile, *turns));
+    }
+    assert!(actions.iter().all(|a| a.turns == 6.0));
+}
+
+// the ura dora indicators are not taken from the draws, so riichi and dama draw as many tiles.  the waits are dead and
+// no two draws make a pair, so neither wins.
+#[test]
+fn riichi_same_draws() {
+    let hand = parse_tile_set("123m456p789s1113z5z").unwrap();
+    let wall = parse_tile_set("9m19p1s2467z").unwrap();
+    let mut ctx = WinContext::new(WinType::Tsumo);
+    ctx.dora_indicators = vec![27, 28];
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let actions = uct::discard_tile(&hand, &[], &wall, &Horizon::new(5), &ctx, 1 << 10, &mut rng);
+    assert!(actions.iter().any(|a| a.riichi));
+    assert!(actions.iter().all(|a| a.turns == 6.0));
+}
+
+// with a wait which cannot improve, riichi wins no faster than dama but for more, and the search goes to it.
+#[test]
+fn riichi_by_points() {
+    let hand = parse_tile_set("123m456p789s1113z5z").unwrap();
+    let wall = parse_tile_set("9m19p1s245567z").unwrap();
+    let ctx = WinContext::new(WinType::Tsumo);
+    for seed in 0..4 {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let actions = uct::discard_tile(&hand, &[], &wall, &Horizon::new(5), &ctx, 1 << 12, &mut rng);
+        let count = |riichi| {
+            actions
+                .iter()
+                .find(|a| a.tile == 29 && a.riichi == riichi)
+                .unwrap()
+                .count
+        };
+        assert!(count(true) > 8 * count(false));
+    }
+}
+
+// the melds are scored with the hand, and an open hand cannot declare riichi.
+#[test]
+fn open_hand() {
+    let hand = parse_tile_set("123m456p78s99s1z").unwrap();
+    let melds = [Meld::Pon(31)];
+    let wall = remaining_tiles(&[&hand, &melds[0].tiles()]).unwrap();
+    let ctx = WinContext::new(WinType::Tsumo);
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let actions = uct::discard_tile(&hand, &melds, &wall, &Horizon::new(10), &ctx, 1 << 12, &mut rng);
+    assert!(actions.iter().all(|a| !a.riichi));
+    assert!(actions.iter().find(|a| a.tile == 27).unwrap().points > 0.0);
+}
@@ -0,0 +1,79 @@
+use teff::decompose::Wait;
+use teff::table::*;
+use teff::tile::*;
+use teff::wait::*;
+
+fn tiles(text: &str) -> TileSet {
+    parse_tile_set(text).unwrap()
+}
+
+fn waits(hand: &str) -> Vec<(String, Vec<Wait>)> {
+    let wall = TableState::new().wall(&tiles(hand)).unwrap();
+    winning_tiles(&tiles(hand), &wall)
+        .into_iter()
+        .map(|w| (format_tile(w.tile), w.shapes))
+        .collect()
+}
+
+fn wait(tile: &str, shapes: &[Wait]) -> (String, Vec<Wait>) {
+    (tile.to_string(), shapes.to_vec())
+}
+
+#[test]
+fn shapes() {
+    use Wait::*;
+    assert_eq!(
+        waits("123m456p23789s77z"),
+        [wait("1s", &[Ryanmen]), wait("4s", &[Ryanmen])]
+    );
+    assert_eq!(waits("123m456p13789s77z"), [wait("2s", &[Kanchan])]);
+    assert_eq!(waits("123m456p12789s77z"), [wait("3s", &[Penchan])]);
+    assert_eq!(
+        waits("123m456p789s1122z"),
+        [wait("1z", &[Shanpon]), wait("2z", &[Shanpon])]
+    );
+    assert_eq!(waits("123m456p789s1112z"), [wait("2z", &[Tanki])]);
+    // 3334s: 333s 4s waits on 4s, 33s 34s on 2s and 5s.
+    assert_eq!(
+        waits("123m456p789s3334s"),
+        [wait("2s", &[Ryanmen]), wait("4s", &[Tanki]), wait("5s", &[Ryanmen])]
+    );
+    // 3334599s: 333s 45s 99s or 33s 345s 99s.
+    assert_eq!(
+        waits("123m456p3334599s"),
+        [
+            wait("3s", &[Ryanmen, Shanpon]),
+            wait("6s", &[Ryanmen]),
+            wait("9s", &[Shanpon])
+        ]
+    );
+    assert_eq!(waits("1133m5577p99s112z"), [wait("2z", &[Tanki])]);
+    assert_eq!(waits("19m19p19s1234567z").len(), 13);
+    assert!(waits("13579m13579p135s").is_empty());
+}
+
+#[test]
+fn nobetan_and_live_tiles() {
+    let hand = tiles("123m456p789s2345s");
+    let mut table = TableState::new();
+    table.discards = tiles("22s");
+    let wall = table.wall(&hand).unwrap();
+    let w = winning_tiles(&hand, &wall);
+    assert_eq!(w.iter().map(|w| w.tile).collect::<Vec<_>>(), [19, 22]);
+    assert_eq!(w[0].n_live, 1);
+    assert_eq!(w[1].n_live, 3);
+    assert!(w.iter().all(|e| is_nobetan(&w, e)));
+    assert_eq!(format_wait(&w, &w[0], Wait::Tanki), "nobetan");
+}
+
+#[test]
+fn furiten() {
+    let hand = tiles("123m456p23789s77z");
+    let wall = TableState::new().wall(&hand).unwrap();
+    let w = winning_tiles(&hand, &wall);
+    assert!(!is_furiten(&w, &TileSet::new()));
+    assert!(!is_furiten(&w, &tiles("5s7z")));
+    // a discard of either end of the wait is furiten.
+    assert!(is_furiten(&w, &tiles("4s")));
+    assert!(is_furiten(&w, &tiles("1s")));
+}